@@ -0,0 +1,138 @@
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parenthesized, Attribute, Expr, Ident, LitInt, Token};
+
+/// One argument in attributes like `#[config_field(...)]`, either a flag (`optional`),
+/// a value (`default = 123`), a list of arguments (`profile(dev = 1, prod = 2)`) or a value
+/// for a number (`migrate(1 = v1_to_v2)`).
+pub enum AttrArg {
+    Flag(Ident),
+    Value(Ident, Box<Expr>),
+    List(Ident, Vec<AttrArg>),
+    Indexed(LitInt, Box<Expr>),
+}
+
+impl AttrArg {
+    pub fn name(&self) -> String {
+        match self {
+            AttrArg::Flag(name) | AttrArg::Value(name, _) | AttrArg::List(name, _) => {
+                name.to_string()
+            }
+            AttrArg::Indexed(index, _) => index.to_string(),
+        }
+    }
+}
+
+impl Parse for AttrArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitInt) {
+            let index = input.parse()?;
+            input.parse::<Token![=]>()?;
+            return Ok(AttrArg::Indexed(index, Box::new(input.parse()?)));
+        }
+        // `parse_any` allows keywords like `type` to be used as argument names
+        let name: Ident = input.call(Ident::parse_any)?;
+        if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            Ok(AttrArg::Value(name, Box::new(input.parse()?)))
+        } else if input.peek(syn::token::Paren) {
+            let content;
+            parenthesized!(content in input);
+            let args = Punctuated::<AttrArg, Token![,]>::parse_terminated(&content)?;
+            Ok(AttrArg::List(name, args.into_iter().collect()))
+        } else {
+            Ok(AttrArg::Flag(name))
+        }
+    }
+}
+
+/// Parses the arguments of all attributes with the given name, so
+/// `#[config_field(default = 1)] #[config_field(optional)]` is the same as
+/// `#[config_field(default = 1, optional)]`.
+pub fn attr_args(attrs: &[Attribute], attr_name: &str) -> Vec<AttrArg> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident(attr_name))
+        .flat_map(|attr| {
+            attr.parse_args_with(Punctuated::<AttrArg, Token![,]>::parse_terminated)
+                .unwrap_or_else(|e| panic!("could not parse #[{}(...)]: {}", attr_name, e))
+        })
+        .collect()
+}
+
+pub fn find_value<'a>(args: &'a [AttrArg], name: &str) -> Option<&'a Expr> {
+    args.iter().find_map(|arg| match arg {
+        AttrArg::Value(ident, value) if ident == name => Some(value.as_ref()),
+        _ => None,
+    })
+}
+
+pub fn find_list<'a>(args: &'a [AttrArg], name: &str) -> Option<&'a [AttrArg]> {
+    args.iter().find_map(|arg| match arg {
+        AttrArg::List(ident, args) if ident == name => Some(args.as_slice()),
+        _ => None,
+    })
+}
+
+pub fn has_flag(args: &[AttrArg], name: &str) -> bool {
+    args.iter()
+        .any(|arg| matches!(arg, AttrArg::Flag(ident) if ident == name))
+}
+
+/// Returns the doc comments from `attrs` as a single string, with each line trimmed.
+pub fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(syn::Meta::NameValue(syn::MetaNameValue {
+                lit: syn::Lit::Str(lit),
+                ..
+            })) => Some(lit.value().trim().to_string()),
+            _ => None,
+        })
+        .collect();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n").trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_config_field_args() {
+        let attr: syn::DeriveInput = syn::parse_quote! {
+            #[config_field(default = [127, 0, 0, 1], optional)]
+            #[config_field(profile(dev = "dev", prod = 1 + 2))]
+            struct Test;
+        };
+        let args = attr_args(&attr.attrs, "config_field");
+        assert_eq!(args.len(), 3);
+        assert!(find_value(&args, "default").is_some());
+        assert!(matches!(&args[1], AttrArg::Flag(name) if name == "optional"));
+        let profiles = find_list(&args, "profile").unwrap();
+        assert_eq!(
+            profiles.iter().map(|arg| arg.name()).collect::<Vec<_>>(),
+            vec!["dev", "prod"]
+        );
+    }
+
+    #[test]
+    fn parse_indexed_args() {
+        let attr: syn::DeriveInput = syn::parse_quote! {
+            #[app_config(version = 3, migrate(1 = v1_to_v2, 2 = v2_to_v3))]
+            struct Test;
+        };
+        let args = attr_args(&attr.attrs, "app_config");
+        let migrations = find_list(&args, "migrate").unwrap();
+        assert!(
+            matches!(&migrations[0], AttrArg::Indexed(index, _) if index.base10_digits() == "1")
+        );
+        assert_eq!(migrations[1].name(), "2");
+    }
+}
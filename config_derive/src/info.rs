@@ -0,0 +1,162 @@
+use crate::attrs::doc_comment;
+use crate::attrs::{attr_args, find_value};
+use crate::rename::RenameRule;
+use crate::{
+    default_field_value, default_variant, enum_tag, field_aliases, field_name, is_nested_field,
+    is_optional_field, option_type, profile_default_values, struct_aliases, NestedField,
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+use syn::{Attribute, DataEnum, Expr, Field, Ident, Type};
+
+fn doc_tokens(attrs: &[Attribute]) -> TokenStream {
+    match doc_comment(attrs) {
+        Some(doc) => quote! { Some(#doc) },
+        None => quote! { None },
+    }
+}
+
+fn type_name(ty: &Type) -> String {
+    quote!(#ty).to_string().replace(' ', "")
+}
+
+// Evaluates the default value the same way as `new_default`, and formats it with `Display`
+fn display_default(ty: &Type, optional: bool, value: &Expr) -> TokenStream {
+    if optional {
+        quote! {{
+            let default: Option<#ty> = #value.into();
+            default
+                .as_ref()
+                .and_then(|default| (&::app_config::interpolate::Value(default)).interpolation_value())
+        }}
+    } else {
+        quote! {{
+            let default: #ty = #value.into();
+            (&::app_config::interpolate::Value(&default)).interpolation_value()
+        }}
+    }
+}
+
+pub fn struct_config_info(
+    struct_name: &Ident,
+    attrs: &[Attribute],
+    fields: &Punctuated<Field, Comma>,
+) -> TokenStream {
+    let rename_all = RenameRule::from_attrs(attrs);
+    let field_infos = fields.iter().map(|f| {
+        let name = field_name(f, rename_all);
+        let doc = doc_tokens(&f.attrs);
+        let nested = match is_nested_field(f) {
+            Some(
+                NestedField::Nested(ty)
+                | NestedField::NestedOptional(ty)
+                | NestedField::NestedPointer(ty),
+            ) => quote! { Some(<#ty as AppConfig>::config_info) },
+            None => quote! { None },
+        };
+        let optional = is_optional_field(f);
+        let ty = optional.clone().unwrap_or_else(|| f.ty.clone());
+        // aliases made optional with `#[config_field(optional)]` are shown by their own name
+        let ty_name = match option_type(&f.ty) {
+            None if optional.is_some() => type_name(&f.ty),
+            _ => type_name(&ty),
+        };
+        let is_optional = optional.is_some();
+        let default_value = default_field_value(f);
+        let has_default = default_value.is_some();
+        let default = match default_value {
+            Some(value) if is_nested_field(f).is_none() => {
+                display_default(&ty, is_optional, &value)
+            }
+            _ => quote! { None },
+        };
+        let profile_defaults = profile_default_values(f)
+            .into_iter()
+            .map(|(profile, value)| {
+                let profile = profile.to_string();
+                let default = display_default(&ty, is_optional, &value);
+                quote! { (#profile, #default) }
+            });
+        let aliases = field_aliases(f);
+        let deprecated = match find_value(&attr_args(&f.attrs, "config_field"), "deprecated") {
+            Some(message) => quote! { Some(#message) },
+            None => quote! { None },
+        };
+        quote! {
+            ::app_config::info::FieldInfo {
+                name: #name,
+                ty: #ty_name,
+                doc: #doc,
+                optional: #is_optional,
+                has_default: #has_default,
+                default: #default,
+                profile_defaults: vec![#(#profile_defaults ),*],
+                nested: #nested,
+                aliases: vec![#(#aliases ),*],
+                deprecated: #deprecated,
+            }
+        }
+    });
+    let aliases = struct_aliases(attrs).into_iter().map(|(old, new)| {
+        let old = old.to_string();
+        quote! { (#old, #new) }
+    });
+    let doc = doc_tokens(attrs);
+    quote! {
+        #[allow(unused_imports, clippy::useless_conversion)]
+        fn config_info() -> ::app_config::info::ConfigInfo {
+            use ::app_config::interpolate::{DisplayValue, OpaqueValue};
+            ::app_config::info::ConfigInfo::Struct(::app_config::info::StructInfo {
+                name: stringify!(#struct_name),
+                doc: #doc,
+                fields: vec![#(#field_infos ),*],
+                aliases: vec![#(#aliases ),*],
+            })
+        }
+    }
+}
+
+pub fn enum_config_info(
+    struct_name: &Ident,
+    attrs: &[Attribute],
+    data: &DataEnum,
+    variants: &[(Ident, Field, Ident, String)],
+) -> TokenStream {
+    let variant_infos =
+        data.variants
+            .iter()
+            .zip(variants)
+            .map(|(variant, (_, wrapped, _, name))| {
+                let ty = &wrapped.ty;
+                let doc = doc_tokens(&variant.attrs);
+                quote! {
+                    ::app_config::info::VariantInfo {
+                        name: #name,
+                        doc: #doc,
+                        config: <#ty as AppConfig>::config_info,
+                    }
+                }
+            });
+    let doc = doc_tokens(attrs);
+    let tag = enum_tag(attrs);
+    let default = match default_variant(data) {
+        Some(index) => {
+            let name = &variants[index].3;
+            quote! { Some(#name) }
+        }
+        None => quote! { None },
+    };
+    quote! {
+        fn config_info() -> ::app_config::info::ConfigInfo {
+            ::app_config::info::ConfigInfo::Enum(::app_config::info::EnumInfo {
+                name: stringify!(#struct_name),
+                doc: #doc,
+                tag: #tag,
+                default: #default,
+                variants: vec![#(#variant_infos ),*],
+            })
+        }
+    }
+}
@@ -1,17 +1,33 @@
 extern crate proc_macro;
 
+mod attrs;
+mod info;
+mod rename;
+#[cfg(feature = "json-schema")]
+mod schema;
+
+use attrs::{attr_args, find_list, find_value, has_flag, AttrArg};
 use lazy_static::lazy_static;
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote, quote_spanned};
 use regex::Regex;
+use rename::RenameRule;
+use syn::fold::Fold;
+use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
+use syn::token::Comma;
 use syn::{
-    parse_macro_input, Attribute, Data, DeriveInput, Field, Fields, Ident, Lit, Type, Visibility,
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Expr, Field, Fields, Ident, Type,
+    Visibility,
 };
 
-#[proc_macro_derive(AppConfig, attributes(builder_derive, config_field, nested_field))]
+#[proc_macro_derive(
+    AppConfig,
+    attributes(app_config, builder_attr, builder_derive, config_field, nested_field)
+)]
 pub fn app_config_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    let input = ReplaceSelf(&input.ident.clone()).fold_derive_input(input);
 
     let struct_name = &input.ident;
 
@@ -21,30 +37,78 @@ pub fn app_config_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStr
 
     let builder_struct = match input.data {
         Data::Struct(ref data) => declare_impl_builder_struct(
-            &struct_name,
+            struct_name,
             &builder_struct_name,
             data,
             derives,
+            &input.attrs,
             input.vis,
         ),
         Data::Enum(ref data) => declare_impl_builder_enum(
-            &struct_name,
+            struct_name,
             &builder_struct_name,
             data,
             derives,
+            &input.attrs,
             input.vis,
         ),
         _ => unimplemented!(),
     };
 
+    let migrations = migrations(&input.attrs);
+
+    let config_info = match input.data {
+        Data::Struct(ref data) => {
+            info::struct_config_info(struct_name, &input.attrs, named_fields(data))
+        }
+        Data::Enum(ref data) => info::enum_config_info(
+            struct_name,
+            &input.attrs,
+            data,
+            &enum_variants(data, &input.attrs),
+        ),
+        _ => unimplemented!(),
+    };
+
+    #[cfg(feature = "json-schema")]
+    let json_schema = match input.data {
+        Data::Struct(ref data) => {
+            schema::impl_struct_json_schema(struct_name, &input.attrs, named_fields(data))
+        }
+        Data::Enum(ref data) => schema::impl_enum_json_schema(
+            struct_name,
+            &input.attrs,
+            data,
+            &enum_variants(data, &input.attrs),
+        ),
+        _ => unimplemented!(),
+    };
+    #[cfg(not(feature = "json-schema"))]
+    let json_schema = quote! {};
+
     let gen = quote! {
         #builder_struct
+        #json_schema
         impl AppConfig for #struct_name {
             type Builder = #builder_struct_name;
 
             fn builder() -> #builder_struct_name {
                 #builder_struct_name::new()
             }
+
+            #config_info
+        }
+        impl ::app_config::AppConfigBuilder for #builder_struct_name {
+            fn new() -> Self {
+                #builder_struct_name::new()
+            }
+            fn combine(self, other: Self) -> Self {
+                #builder_struct_name::combine(self, other)
+            }
+            fn set_fields(&self, prefix: &str, fields: &mut Vec<String>) {
+                #builder_struct_name::set_fields(self, prefix, fields)
+            }
+            #migrations
         }
     };
     gen.into()
@@ -55,44 +119,98 @@ fn declare_impl_builder_struct(
     builder_struct_name: &Ident,
     data: &syn::DataStruct,
     derives: Option<TokenStream>,
+    attrs: &[Attribute],
     vis: Visibility,
 ) -> TokenStream {
-    let fields = match &data.fields {
-        Fields::Named(fields) => &fields.named,
-        _ => unimplemented!(),
-    };
+    let fields = named_fields(data);
+    let deserialize = derives_trait(attrs, "Deserialize");
+    let serde_default = serde_default(builder_struct_name, deserialize);
+    let container_attrs = builder_attrs(attrs);
+    let rename_all = RenameRule::from_attrs(attrs);
+    let deserialize_fns = fields.iter().filter_map(|f| {
+        if !deserialize || is_nested_field(f).is_some() {
+            return None;
+        }
+        let parser = field_parser(f)?;
+        let ty = &f.ty;
+        let fn_name = deserialize_fn_name(builder_struct_name, f);
+        // null is an explicit `None` for optional fields, clears collections, and leaves other
+        // fields unset
+        let value = if is_optional_field(f).is_some() {
+            quote! { .map(Some) }
+        } else if is_collection_type(ty) {
+            quote! { .map(|value| Some(value.unwrap_or_default())) }
+        } else {
+            quote! {}
+        };
+        Some(quote! {
+            #[allow(non_snake_case)]
+            fn #fn_name<'de, D: ::serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Option<#ty>, D::Error> {
+                #[allow(unused_imports)]
+                use ::app_config::parse::{DeserializeScalar, DeserializeStructured};
+                (&::app_config::parse::Parser::new(#parser)).deserialize(deserializer)#value
+            }
+        })
+    });
     let declare_fields = fields.iter().map(|f| {
         let ty = &f.ty;
         let ident = &f.ident;
-        if let Some(NestedField::Nested) = is_nested_field(f) {
+        let attrs = builder_attrs(&f.attrs);
+        let rename = serde_rename(
+            deserialize,
+            &ident.as_ref().unwrap().to_string(),
+            &field_name(f, rename_all),
+        );
+        let attrs = quote! { #rename #attrs };
+        let deserialize_optional = if deserialize {
+            quote! { #[serde(default, deserialize_with = "::app_config::deserialize_optional")] }
+        } else {
+            quote! {}
+        };
+        if let Some(NestedField::Nested(ty)) = is_nested_field(f) {
             quote! {
+                #attrs
                 pub #ident: <#ty as AppConfig>::Builder,
             }
-        } else if let Some(NestedField::NestedOptional(ty)) = is_nested_field(f) {
+        } else if let Some((ty, pointer)) = optional_nested_field(f) {
+            let builder = if pointer {
+                quote! { Box<<#ty as AppConfig>::Builder> }
+            } else {
+                quote! { <#ty as AppConfig>::Builder }
+            };
             quote! {
-                pub #ident: <#ty as AppConfig>::Builder,
+                #deserialize_optional
+                #attrs
+                pub #ident: Option<Option<#builder>>,
+            }
+        } else if deserialize && field_parser(f).is_some() {
+            let fn_name = deserialize_fn_name(builder_struct_name, f).to_string();
+            quote! {
+                #[serde(default, deserialize_with = #fn_name)]
+                #attrs
+                pub #ident: Option<#ty>,
+            }
+        } else if is_optional_field(f).is_some() {
+            quote! {
+                #deserialize_optional
+                #attrs
+                pub #ident: Option<#ty>,
             }
         } else {
             quote! {
+                #attrs
                 pub #ident: Option<#ty>,
             }
         }
     });
     let field_empty = fields.iter().map(|f| {
-        let ty = &f.ty;
         let ident = &f.ident;
-        if let Some(NestedField::Nested) = is_nested_field(f) {
-            quote_spanned! {f.span()=>
-                #ident: <#ty as AppConfig>::Builder::new(),
-            }
-        } else if let Some(NestedField::NestedOptional(ty)) = is_nested_field(f) {
+        if let Some(NestedField::Nested(ty)) = is_nested_field(f) {
             quote_spanned! {f.span()=>
                 #ident: <#ty as AppConfig>::Builder::new(),
             }
-        } else if is_optional_field(f).is_some() {
-            quote_spanned! {f.span()=>
-                #ident: Some(None),
-            }
         } else {
             quote! {
                 #ident: None,
@@ -100,85 +218,132 @@ fn declare_impl_builder_struct(
         }
     });
     let field_defaults = fields.iter().map(|f| {
-        let ty = &f.ty;
         let ident = &f.ident;
-        if let Some(NestedField::Nested) = is_nested_field(f) {
-            quote_spanned! {f.span()=>
-                #ident: <#ty as AppConfig>::Builder::new_default(),
-            }
-        } else if let Some(NestedField::NestedOptional(ty)) = is_nested_field(f) {
-            quote_spanned! {f.span()=>
-                #ident: <#ty as AppConfig>::Builder::new(),
-            }
-        } else if let Some(default_value) = default_field_value(f) {
-            quote_spanned! {f.span()=>
-                #ident: Some(#default_value.into()),
-            }
-        } else if is_optional_field(f).is_some() {
+        if let Some(NestedField::Nested(ty)) = is_nested_field(f) {
             quote_spanned! {f.span()=>
-                #ident: Some(None),
+                #ident: <#ty as AppConfig>::Builder::new_default_for_profile(profile),
             }
-        } else {
+        } else if is_nested_field(f).is_some() {
             quote! {
                 #ident: None,
             }
+        } else {
+            let default_value = if let Some(default_value) = default_field_value(f) {
+                quote_spanned! {f.span()=>
+                    Some(#default_value.into())
+                }
+            } else {
+                quote! {
+                    None
+                }
+            };
+            let profile_defaults = profile_default_values(f)
+                .into_iter()
+                .map(|(profile, value)| {
+                    let profile = profile.to_string();
+                    quote_spanned! {f.span()=>
+                        Some(#profile) => Some(#value.into()),
+                    }
+                })
+                .collect::<Vec<_>>();
+            if profile_defaults.is_empty() {
+                quote! {
+                    #ident: #default_value,
+                }
+            } else {
+                quote! {
+                    #ident: match profile {
+                        #(#profile_defaults )*
+                        _ => #default_value,
+                    },
+                }
+            }
         }
     });
     let fields_not_set = fields.iter().map(|f| {
         let ident = &f.ident;
-        if let Some(NestedField::Nested) = is_nested_field(f) {
+        if let Some(NestedField::Nested(_)) = is_nested_field(f) {
             quote! {
                 if !self.#ident.is_empty() {
                     return false;
                 }
             }
-        } else if let Some(NestedField::NestedOptional(_)) = is_nested_field(f) {
+        } else if is_nested_field(f).is_some() {
+            // a cleared section is set, and a section without values isn't
             quote! {
-                if !self.#ident.is_empty() {
-                    return false;
+                match &self.#ident {
+                    None => {}
+                    Some(Some(builder)) if builder.is_empty() => {}
+                    Some(_) => return false,
                 }
             }
-        } else if is_optional_field(f).is_some() {
-            quote_spanned! {f.span()=>
-                if !self.#ident.as_ref().map(|b| b.is_none()).unwrap_or(true) {
+        } else {
+            quote! {
+                if self.#ident.is_some() {
                     return false;
                 }
             }
+        }
+    });
+    let collect_set_fields = fields.iter().map(|f| {
+        let name = field_name(f, rename_all);
+        let ident = &f.ident;
+        let path = quote! {
+            ::app_config::join_path(prefix, #name)
+        };
+        if let Some(NestedField::Nested(_)) = is_nested_field(f) {
+            quote! {
+                self.#ident.set_fields(&#path, fields);
+            }
+        } else if is_nested_field(f).is_some() {
+            // a cleared section is set, like a field that is set to null
+            quote! {
+                match &self.#ident {
+                    Some(Some(builder)) => builder.set_fields(&#path, fields),
+                    Some(None) => fields.push(#path),
+                    None => {}
+                }
+            }
         } else {
             quote! {
                 if self.#ident.is_some() {
-                    return false;
+                    fields.push(#path);
                 }
             }
         }
     });
     let check_missing_fields = fields.iter().map(|f| {
+        let name = field_name(f, rename_all);
         let ident = &f.ident;
-        if let Some(NestedField::Nested) = is_nested_field(f) {
-            quote! {}
-        } else if let Some(NestedField::NestedOptional(_)) = is_nested_field(f) {
+        if is_nested_field(f).is_some() || is_optional_field(f).is_some() {
             quote! {}
         } else {
             quote! {
                 if self.#ident.is_none() {
-                    missing_fields.push(stringify!(#ident));
+                    missing_fields.push(#name);
                 }
             }
         }
     });
     let assign_fields = fields.iter().map(|f| {
         let ident = &f.ident;
-        if let Some(NestedField::Nested) = is_nested_field(f) {
+        // `into` wraps nested configs in pointers like `Arc<Config>`
+        if let Some(NestedField::Nested(_)) = is_nested_field(f) {
             quote! {
-                #ident: self.#ident.try_build()?,
+                #ident: self.#ident.try_build()?.into(),
             }
-        } else if let Some(NestedField::NestedOptional(_)) = is_nested_field(f) {
+        } else if let Some((_, pointer)) = optional_nested_field(f) {
+            let builder = unboxed(pointer, quote! { builder });
             quote! {
-                #ident: if self.#ident.is_empty() {
-                        None
-                    } else {
-                        Some(self.#ident.try_build()?)
-                    },
+                #ident: match self.#ident {
+                    Some(Some(builder)) if !builder.is_empty() => Some(#builder.try_build()?.into()),
+                    _ => None,
+                },
+            }
+        } else if is_optional_field(f).is_some() {
+            // unset optional fields are `None`, like fields that are explicitly set to null
+            quote! {
+                #ident: self.#ident.flatten(),
             }
         } else {
             quote! {
@@ -188,13 +353,21 @@ fn declare_impl_builder_struct(
     });
     let combine_fields = fields.iter().map(|f| {
         let ident = &f.ident;
-        if let Some(NestedField::Nested) = is_nested_field(f) {
+        if let Some(NestedField::Nested(_)) = is_nested_field(f) {
             quote! {
                 self.#ident = self.#ident.combine(other.#ident);
             }
-        } else if let Some(NestedField::NestedOptional(_)) = is_nested_field(f) {
+        } else if let Some((_, pointer)) = optional_nested_field(f) {
+            // sections are combined, and a cleared section is only kept when it's set first
+            let builder = unboxed(pointer, quote! { builder });
+            let other = unboxed(pointer, quote! { other });
+            let combined = boxed(pointer, quote! { #builder.combine(#other) });
             quote! {
-                self.#ident = self.#ident.combine(other.#ident);
+                self.#ident = match (self.#ident, other.#ident) {
+                    (Some(Some(builder)), Some(Some(other))) => Some(Some(#combined)),
+                    (None, other) => other,
+                    (builder, _) => builder,
+                };
             }
         } else {
             quote! {
@@ -206,7 +379,7 @@ fn declare_impl_builder_struct(
         let ty = &f.ty;
         let ident = f.ident.as_ref().unwrap();
         let map_ident = format_ident!("map_{}", &ident);
-        if let Some(NestedField::Nested) = is_nested_field(f) {
+        if let Some(NestedField::Nested(ty)) = is_nested_field(f) {
             quote! {
                 pub fn #ident(mut self, value: <#ty as AppConfig>::Builder) -> Self {
                     self.#ident = value;
@@ -217,14 +390,26 @@ fn declare_impl_builder_struct(
                     self
                 }
             }
-        } else if let Some(NestedField::NestedOptional(ty)) = is_nested_field(f) {
+        } else if let Some((ty, pointer)) = optional_nested_field(f) {
+            let clear_ident = format_ident!("clear_{}", &ident);
+            let value = boxed(pointer, quote! { value });
+            let mapped = boxed(pointer, quote! { (map)(builder) });
+            let builder = unboxed(pointer, quote! { builder });
             quote! {
                 pub fn #ident(mut self, value: <#ty as AppConfig>::Builder) -> Self {
-                    self.#ident = value;
+                    self.#ident = Some(Some(#value));
                     self
                 }
                 pub fn #map_ident(mut self, map: fn(<#ty as AppConfig>::Builder) -> <#ty as AppConfig>::Builder) -> Self {
-                    self.#ident = (map)(self.#ident);
+                    let builder = match self.#ident {
+                        Some(Some(builder)) => #builder,
+                        _ => <#ty as AppConfig>::Builder::new(),
+                    };
+                    self.#ident = Some(Some(#mapped));
+                    self
+                }
+                pub fn #clear_ident(mut self) -> Self {
+                    self.#ident = Some(None);
                     self
                 }
             }
@@ -238,22 +423,49 @@ fn declare_impl_builder_struct(
         }
     });
     let field_from_env_functions = fields.iter().map(|f| {
+        let name = field_name(f, rename_all);
         let ty = &f.ty;
         let ident = f.ident.as_ref().unwrap();
         let fn_name = format_ident!("{}_from_env", ident);
-        if let Some(NestedField::Nested) = is_nested_field(f) {
+        let source_fn_name = format_ident!("{}_from_source", ident);
+        if let Some(NestedField::Nested(ty)) = is_nested_field(f) {
             quote_spanned! {f.span()=>
                 pub fn #fn_name(&mut self, prefix: &str) -> Result<(), Vec<String>> {
-                    let prefix = format!("{}_{}", prefix, stringify!(#ident));
-                    self.#ident = <#ty as AppConfig>::builder().from_env_prefixed(&prefix)?;
+                    self.#source_fn_name("", &::app_config::source::EnvSource::new(prefix))
+                }
+                pub fn #source_fn_name(&mut self, path: &str, source: &dyn ::app_config::source::Source) -> Result<(), Vec<String>> {
+                    let path = ::app_config::join_path(path, #name);
+                    self.#ident = <#ty as AppConfig>::Builder::new_from_source_prefixed(&path, source)?;
                     Ok(())
                 }
             }
-        } else if let Some(NestedField::NestedOptional(ty)) = is_nested_field(f) {
+        } else if let Some((ty, pointer)) = optional_nested_field(f) {
+            let builder = boxed(pointer, quote! { builder });
+            let clear = if clear_on_empty(f) {
+                quote! {
+                    if source.get(&path).map_err(|e| vec![e])?.as_deref() == Some("") {
+                        self.#ident = Some(None);
+                        return Ok(());
+                    }
+                }
+            } else {
+                quote! {}
+            };
+            // only loaded when the source has values for it, so configs that contain
+            // themselves don't recurse forever
             quote! {
                 pub fn #fn_name(&mut self, prefix: &str) -> Result<(), Vec<String>> {
-                    let prefix = format!("{}_{}", prefix, stringify!(#ident));
-                    self.#ident = <#ty as AppConfig>::builder().from_env_prefixed(&prefix)?;
+                    self.#source_fn_name("", &::app_config::source::EnvSource::new(prefix))
+                }
+                pub fn #source_fn_name(&mut self, path: &str, source: &dyn ::app_config::source::Source) -> Result<(), Vec<String>> {
+                    let path = ::app_config::join_path(path, #name);
+                    #clear
+                    self.#ident = if source.has_prefix(&path) {
+                        let builder = <#ty as AppConfig>::Builder::new_from_source_prefixed(&path, source)?;
+                        Some(#builder).filter(|builder| !builder.is_empty()).map(Some)
+                    } else {
+                        None
+                    };
                     Ok(())
                 }
             }
@@ -271,44 +483,154 @@ fn declare_impl_builder_struct(
                 };
                 (ty, set_value)
             };
+            // `#[config_field(clear_on_empty)]` clears the value set by later sources
+            let clear = match (clear_on_empty(f), is_optional_field(f)) {
+                (false, _) => quote! {},
+                (true, Some(_)) => quote! {
+                    Some(raw) if raw.is_empty() => {
+                        self.#ident = Some(None);
+                        Ok(())
+                    }
+                },
+                (true, None) if is_collection_type(&f.ty) => quote! {
+                    Some(raw) if raw.is_empty() => {
+                        self.#ident = Some(Default::default());
+                        Ok(())
+                    }
+                },
+                (true, None) => panic!(
+                    "`clear_on_empty` is only supported on optional fields, optional nested \
+                     fields and collections"
+                ),
+            };
+            let parse = field_parse(f, &ty);
             quote_spanned! {f.span()=>
                 pub fn #fn_name(&mut self, prefix: &str) -> Result<(), String> {
-                    let env_name = format!("{}_{}", prefix, stringify!(#ident)).to_uppercase();
-                    match std::env::var(&env_name).map(|value| (<#ty as std::str::FromStr>::from_str(&value), value)) {
-                        Ok((Ok(value), _)) => {
-                            #set_value
-                            Ok(())
+                    self.#source_fn_name("", &::app_config::source::EnvSource::new(prefix))
+                }
+                pub fn #source_fn_name(&mut self, path: &str, source: &dyn ::app_config::source::Source) -> Result<(), String> {
+                    let path = ::app_config::join_path(path, #name);
+                    match source.get(&path)? {
+                        #clear
+                        Some(raw) => match #parse {
+                            Ok(value) => {
+                                #set_value
+                                Ok(())
+                            }
+                            Err(e) => Err(::app_config::parse::with_error(
+                                format!("could not parse {}={}", source.describe(&path), raw),
+                                e,
+                            )),
                         },
-                        Ok((Err(_), raw)) => Err(format!(
-                            "could not parse environment varaible {}={}",
-                            env_name, raw
-                        )),
-                        Err(std::env::VarError::NotPresent) => Ok(()),
-                        _ => Err(format!("could not read environment varaible {}", env_name)),
+                        None => Ok(()),
                     }
                 }
             }
         }
     });
-    let load_field_from_env = fields.iter().map(|f| {
+    let load_field_from_source = fields.iter().map(|f| {
         let ident = f.ident.as_ref().unwrap();
-        let fn_name = format_ident!("{}_from_env", &ident);
-        if let Some(NestedField::Nested) = is_nested_field(f) {
+        let fn_name = format_ident!("{}_from_source", &ident);
+        if is_nested_field(f).is_some() {
             quote_spanned! {f.span()=>
-                if let Err(mut e) = builder.#fn_name(prefix) {
+                if let Err(mut e) = builder.#fn_name(path, source) {
                     failed_fields.append(&mut e);
                 }
             }
-        } else if let Some(NestedField::NestedOptional(_ty)) = is_nested_field(f) {
+        } else {
             quote! {
-                if let Err(mut e) = builder.#fn_name(prefix) {
-                    failed_fields.append(&mut e);
+                if let Err(e) = builder.#fn_name(path, source) {
+                    failed_fields.push(e);
+                }
+            }
+        }
+    });
+    let collect_interpolation_values = fields.iter().map(|f| {
+        let name = field_name(f, rename_all);
+        let ident = &f.ident;
+        let insert_value = quote! {
+            if let Some(value) = (&::app_config::interpolate::Value(value)).interpolation_value() {
+                values.insert(::app_config::join_path(prefix, #name), value);
+            }
+        };
+        if let Some(NestedField::Nested(_)) = is_nested_field(f) {
+            quote! {
+                self.#ident.interpolation_values(
+                    &::app_config::join_path(prefix, #name),
+                    values,
+                );
+            }
+        } else if is_nested_field(f).is_some() {
+            quote! {
+                if let Some(Some(builder)) = &self.#ident {
+                    builder.interpolation_values(
+                        &::app_config::join_path(prefix, #name),
+                        values,
+                    );
+                }
+            }
+        } else if is_optional_field(f).is_some() {
+            quote_spanned! {f.span()=>
+                if let Some(Some(value)) = &self.#ident {
+                    #insert_value
                 }
             }
         } else {
+            quote_spanned! {f.span()=>
+                if let Some(value) = &self.#ident {
+                    #insert_value
+                }
+            }
+        }
+    });
+    let apply_interpolated_fields = fields.iter().map(|f| {
+        let name = field_name(f, rename_all);
+        let ident = &f.ident;
+        let path = quote! {
+            ::app_config::join_path(prefix, #name)
+        };
+        if let Some(NestedField::Nested(_)) = is_nested_field(f) {
             quote! {
-                if let Err(e) = builder.#fn_name(prefix) {
-                    failed_fields.push(e);
+                self.#ident.apply_interpolated(&#path, resolved, errors);
+            }
+        } else if is_nested_field(f).is_some() {
+            quote! {
+                if let Some(Some(builder)) = &mut self.#ident {
+                    builder.apply_interpolated(&#path, resolved, errors);
+                }
+            }
+        } else if is_optional_field(f)
+            .map(|ty| is_string_type(&ty))
+            .unwrap_or(false)
+        {
+            quote! {
+                if let Some(value) = resolved.get(&#path) {
+                    self.#ident = Some(Some(value.clone()));
+                }
+            }
+        } else if is_string_type(&f.ty) {
+            quote! {
+                if let Some(value) = resolved.get(&#path) {
+                    self.#ident = Some(value.clone());
+                }
+            }
+        } else {
+            // other types are parsed from the resolved value, like values from sources
+            let (ty, value) = match is_optional_field(f) {
+                Some(ty) => (ty, quote! { Some(Some(value)) }),
+                None => (f.ty.clone(), quote! { Some(value) }),
+            };
+            let parse = field_parse(f, &ty);
+            quote_spanned! {f.span()=>
+                let path = #path;
+                if let Some(raw) = resolved.get(&path) {
+                    match #parse {
+                        Ok(value) => self.#ident = #value,
+                        Err(e) => errors.push(::app_config::parse::with_error(
+                            format!("{}: could not parse {:?}", path, raw),
+                            e,
+                        )),
+                    }
                 }
             }
         }
@@ -321,10 +643,13 @@ fn declare_impl_builder_struct(
     quote! {
         #[allow(dead_code)]
         #derives
+        #serde_default
+        #container_attrs
         #vis struct #builder_struct_name {
             #(#declare_fields )*
         }
-        #[allow(dead_code)]
+        #(#deserialize_fns )*
+        #[allow(dead_code, clippy::useless_conversion, clippy::ptr_arg)]
         impl #builder_struct_name {
             pub fn new() -> #builder_struct_name {
                 #builder_struct_name {
@@ -332,6 +657,10 @@ fn declare_impl_builder_struct(
                 }
             }
             pub fn new_default() -> #builder_struct_name {
+                Self::new_default_for_profile(::app_config::profile::active_profile().as_deref())
+            }
+            #[allow(unused_variables)]
+            pub fn new_default_for_profile(profile: Option<&str>) -> #builder_struct_name {
                 #builder_struct_name {
                     #(#field_defaults )*
                 }
@@ -339,10 +668,16 @@ fn declare_impl_builder_struct(
             pub fn default(self) -> #builder_struct_name {
                 Self::new_default()
             }
+            pub fn default_for_profile(self, profile: Option<&str>) -> #builder_struct_name {
+                Self::new_default_for_profile(profile)
+            }
             pub fn is_empty(&self) -> bool {
                 #(#fields_not_set )*
                 true
             }
+            pub fn set_fields(&self, prefix: &str, fields: &mut Vec<String>) {
+                #(#collect_set_fields )*
+            }
             pub fn try_build(self) -> Result<#struct_name, Vec<&'static str>> {
                 let mut missing_fields = Vec::new();
                 #(#check_missing_fields )*
@@ -367,16 +702,68 @@ fn declare_impl_builder_struct(
                 Self::new_from_env()
             }
             pub fn new_from_env_prefixed(prefix: &str) -> Result<#builder_struct_name, Vec<String>> {
+                Self::new_from_source_prefixed("", &::app_config::source::EnvSource::new(prefix))
+            }
+            pub fn from_env_prefixed(self, prefix: &str) -> Result<#builder_struct_name, Vec<String>> {
+                Self::new_from_env_prefixed(prefix)
+            }
+            pub fn new_from_env_strict(prefix: &str) -> Result<#builder_struct_name, Vec<String>> {
+                let unknown = <#struct_name as AppConfig>::unknown_env_vars(prefix);
+                match Self::new_from_env_prefixed(prefix) {
+                    Ok(builder) if unknown.is_empty() => Ok(builder),
+                    Ok(_) => Err(unknown),
+                    Err(mut errors) => {
+                        errors.extend(unknown);
+                        Err(errors)
+                    }
+                }
+            }
+            pub fn from_env_strict(self, prefix: &str) -> Result<#builder_struct_name, Vec<String>> {
+                Self::new_from_env_strict(prefix)
+            }
+            pub fn new_from_source(source: &dyn ::app_config::source::Source) -> Result<#builder_struct_name, Vec<String>> {
+                Self::new_from_source_prefixed("", source)
+            }
+            pub fn from_source(self, source: &dyn ::app_config::source::Source) -> Result<#builder_struct_name, Vec<String>> {
+                Self::new_from_source(source)
+            }
+            pub fn new_from_source_prefixed(path: &str, source: &dyn ::app_config::source::Source) -> Result<#builder_struct_name, Vec<String>> {
                 let mut builder = #builder_struct_name::new();
                 let mut failed_fields = Vec::new();
-                #(#load_field_from_env )*
+                #(#load_field_from_source )*
                 if failed_fields.len() > 0 {
                     return Err(failed_fields);
                 }
                 Ok(builder)
             }
-            pub fn from_env_prefixed(self, prefix: &str) -> Result<#builder_struct_name, Vec<String>> {
-                Self::new_from_env_prefixed(prefix)
+            pub fn interpolate(mut self) -> Result<#builder_struct_name, Vec<String>> {
+                let mut values = std::collections::BTreeMap::new();
+                self.interpolation_values("", &mut values);
+                let resolved = ::app_config::interpolate::resolve(&values)?;
+                let mut errors = Vec::new();
+                self.apply_interpolated("", &resolved, &mut errors);
+                if !errors.is_empty() {
+                    return Err(errors);
+                }
+                Ok(self)
+            }
+            pub fn interpolation_values(
+                &self,
+                prefix: &str,
+                values: &mut std::collections::BTreeMap<String, String>,
+            ) {
+                #[allow(unused_imports)]
+                use ::app_config::interpolate::{DisplayValue, OpaqueValue};
+                #(#collect_interpolation_values )*
+            }
+            #[allow(unused_variables)]
+            pub fn apply_interpolated(
+                &mut self,
+                prefix: &str,
+                resolved: &std::collections::BTreeMap<String, String>,
+                errors: &mut Vec<String>,
+            ) {
+                #(#apply_interpolated_fields )*
             }
         }
     }
@@ -387,62 +774,133 @@ fn declare_impl_builder_enum(
     builder_struct_name: &Ident,
     data: &syn::DataEnum,
     derives: Option<TokenStream>,
+    attrs: &[Attribute],
     vis: Visibility,
 ) -> TokenStream {
-    let variants: Vec<_> = data
-        .variants
-        .iter()
-        .map(|variant| {
-            (
-                variant.ident.clone(),
-                match &variant.fields {
-                    Fields::Unnamed(fields) => {
-                        if fields.unnamed.len() != 1 {
-                            panic!(
-                                "expected each enum variant to have one field, {} has {}",
-                                variant.ident,
-                                fields.unnamed.len()
-                            );
-                        }
-                        fields.unnamed.first().unwrap().clone()
-                    }
-                    _ => unimplemented!(),
-                },
-                format_ident!("{}", pascal_to_snake_case(&variant.ident.to_string())),
-            )
-        })
-        .collect();
-    let declare_fields = variants.iter().map(|(_, wrapped, field)| {
+    let variants = enum_variants(data, attrs);
+    let deserialize = derives_trait(attrs, "Deserialize");
+    let container_attrs = builder_attrs(attrs);
+    let tag = enum_tag(attrs);
+    let not_specified = format!("{} is not specified", tag);
+    let using_rename = serde_rename(deserialize, "using", &tag);
+    let externally_tagged = has_flag(&attr_args(attrs, "app_config"), "externally_tagged");
+    let infer_choice = has_flag(&attr_args(attrs, "app_config"), "infer_choice");
+    // the externally tagged form is deserialized into a struct that records which variants
+    // are in the file, and converted to the builder
+    let (serde_default, tagged) = if deserialize && externally_tagged {
+        let tagged_name = format_ident!("{}Tagged", builder_struct_name);
+        let from = tagged_name.to_string();
+        let tagged = declare_impl_tagged(
+            builder_struct_name,
+            &tagged_name,
+            &variants,
+            &format_ident!("{}Choice", struct_name),
+            &tag,
+        );
+        (quote! { #[serde(from = #from)] }, tagged)
+    } else {
+        (serde_default(builder_struct_name, deserialize), quote! {})
+    };
+    let declare_fields =
+        data.variants
+            .iter()
+            .zip(&variants)
+            .map(|(variant, (_, wrapped, field, name))| {
+                let ty = &wrapped.ty;
+                let attrs = builder_attrs(&variant.attrs);
+                let rename = serde_rename(deserialize, &field.to_string(), name);
+                quote! {
+                    #rename
+                    #attrs
+                    pub #field: <#ty as AppConfig>::Builder,
+                }
+            });
+    let field_empty = variants.iter().map(|(_, wrapped, field, _)| {
         let ty = &wrapped.ty;
         quote! {
-            pub #field: <#ty as AppConfig>::Builder,
+            #field: <#ty as AppConfig>::Builder::new(),
         }
     });
-    let field_empty = variants.iter().map(|(_, wrapped, field)| {
+    let field_defaults = variants.iter().map(|(_, wrapped, field, _)| {
         let ty = &wrapped.ty;
         quote! {
-            #field: <#ty as AppConfig>::Builder::new(),
+            #field: <#ty as AppConfig>::Builder::new_default_for_profile(profile),
         }
     });
-    let field_defaults = variants.iter().map(|(_, wrapped, field)| {
-        let ty = &wrapped.ty;
+    let choice_name = format_ident!("{}Choice", struct_name);
+    let default = default_variant(data);
+    // with `infer_choice`, the only variant that is set is selected when `using` isn't set.
+    // The default variant is left out, as its defaults set it, and it's selected anyway when
+    // no other variant is set.
+    let infer_using = if infer_choice {
+        let push_set_variants = variants
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| default != Some(*index))
+            .map(|(_, (variant, _, field, _))| {
+                quote! {
+                    if !self.#field.is_empty() {
+                        set_variants.push(#choice_name::#variant);
+                    }
+                }
+            });
+        quote! {
+            let using = self.using.or_else(|| {
+                let mut set_variants = Vec::new();
+                #(#push_set_variants )*
+                match set_variants[..] {
+                    [using] => Some(using),
+                    _ => None,
+                }
+            });
+        }
+    } else {
+        quote! {
+            let using = self.using;
+        }
+    };
+    let default_using = match default {
+        Some(index) => {
+            let variant = &variants[index].0;
+            quote! {
+                let using = using.or(Some(#choice_name::#variant));
+            }
+        }
+        None => quote! {},
+    };
+    let choice = declare_impl_choice(struct_name, &choice_name, &variants, attrs, &vis);
+    let match_variant = variants.iter().map(|(variant, _, field, _)| {
+        quote! {
+            Some(#choice_name::#variant) => Ok(#struct_name::#variant(self.#field.try_build()?)),
+        }
+    });
+    let variants_not_set = variants.iter().map(|(_, _, field, _)| {
         quote! {
-            #field: <#ty as AppConfig>::Builder::new_default(),
+            if !self.#field.is_empty() {
+                return false;
+            }
         }
     });
-    let match_variant = variants.iter().map(|(variant, _, field)| {
+    // a variant with values but no choice is an error, even for optional enum configs
+    let check_set_variants = variants.iter().map(|(_, _, field, name)| {
+        let set_without_tag = format!("{} is not specified, but {} is set", tag, name);
         quote! {
-            if self.using.as_ref().map(|u| u == stringify!(#field)).unwrap_or(false) {
-                return Ok(#struct_name::#variant(self.#field.try_build()?));
+            if !self.#field.is_empty() {
+                return Err(vec![#set_without_tag]);
             }
         }
     });
-    let combine_fields = variants.iter().map(|(_, _, field)| {
+    let collect_set_fields = variants.iter().map(|(_, _, field, name)| {
+        quote! {
+            self.#field.set_fields(&::app_config::join_path(prefix, #name), fields);
+        }
+    });
+    let combine_fields = variants.iter().map(|(_, _, field, _)| {
         quote! {
             self.#field = self.#field.combine(other.#field);
         }
     });
-    let field_functions = variants.iter().map(|(_, wrapped, field)| {
+    let field_functions = variants.iter().map(|(_, wrapped, field, _)| {
         let ty = &wrapped.ty;
         let map_ident = format_ident!("map_{}", field);
         quote! {
@@ -456,42 +914,77 @@ fn declare_impl_builder_enum(
             }
         }
     });
-    let using_functions = variants.iter().map(|(_, _, field)| {
+    let using_functions = variants.iter().map(|(variant, _, field, _)| {
         let using_ident = format_ident!("using_{}", field);
         quote! {
             pub fn #using_ident(mut self) -> Self {
-                self.using = Some(stringify!(#field).into());
+                self.using = Some(#choice_name::#variant);
                 self
             }
         }
     });
-    let field_from_env_functions = variants.iter().map(|(variant, wrapped, field)| {
+    let field_from_env_functions = variants.iter().map(|(variant, wrapped, field, name)| {
         let ty = &wrapped.ty;
         let fn_name = format_ident!("{}_from_env", field);
+        let source_fn_name = format_ident!("{}_from_source", field);
         quote_spanned! {variant.span()=>
             pub fn #fn_name(&mut self, prefix: &str) -> Result<(), Vec<String>> {
-                let prefix = format!("{}_{}", prefix, stringify!(#field));
-                self.#field = <#ty as AppConfig>::builder().from_env_prefixed(&prefix)?;
+                self.#source_fn_name("", &::app_config::source::EnvSource::new(prefix))
+            }
+            pub fn #source_fn_name(&mut self, path: &str, source: &dyn ::app_config::source::Source) -> Result<(), Vec<String>> {
+                let path = ::app_config::join_path(path, #name);
+                self.#field = <#ty as AppConfig>::Builder::new_from_source_prefixed(&path, source)?;
                 Ok(())
             }
         }
     });
-    let load_field_from_env = variants.iter().map(|(variant, _, field)| {
-        let fn_name = format_ident!("{}_from_env", &field);
+    // `using` is loaded first, so a variant that isn't selected only warns about values it
+    // can't parse. Without `using`, any variant may be selected later, so they're all errors.
+    let load_field_from_source = variants.iter().map(|(variant, _, field, name)| {
+        let fn_name = format_ident!("{}_from_source", &field);
         quote_spanned! {variant.span()=>
-            if let Err(mut e) = builder.#fn_name(prefix) {
-                failed_fields.append(&mut e);
+            if let Err(mut e) = builder.#fn_name(path, source) {
+                if matches!(builder.using, None | Some(#choice_name::#variant)) {
+                    failed_fields.append(&mut e);
+                } else {
+                    let variant_path = ::app_config::join_path(path, #name);
+                    for e in e {
+                        source.warn(&variant_path, format!("ignored because `{}` is not selected: {}", #name, e));
+                    }
+                }
             }
         }
     });
+    let collect_interpolation_values = variants.iter().map(|(_, _, field, name)| {
+        quote! {
+            self.#field.interpolation_values(
+                &::app_config::join_path(prefix, #name),
+                values,
+            );
+        }
+    });
+    let apply_interpolated_fields = variants.iter().map(|(_, _, field, name)| {
+        quote! {
+            self.#field.apply_interpolated(
+                &::app_config::join_path(prefix, #name),
+                resolved,
+                errors,
+            );
+        }
+    });
     quote! {
+        #choice
+        #tagged
         #[allow(dead_code)]
         #derives
+        #serde_default
+        #container_attrs
         #vis struct #builder_struct_name {
-            pub using: Option<String>,
+            #using_rename
+            pub using: Option<#choice_name>,
             #(#declare_fields )*
         }
-        #[allow(dead_code)]
+        #[allow(dead_code, clippy::useless_conversion, clippy::ptr_arg)]
         impl #builder_struct_name {
             pub fn new() -> #builder_struct_name {
                 #builder_struct_name {
@@ -500,6 +993,10 @@ fn declare_impl_builder_enum(
                 }
             }
             pub fn new_default() -> #builder_struct_name {
+                Self::new_default_for_profile(::app_config::profile::active_profile().as_deref())
+            }
+            #[allow(unused_variables)]
+            pub fn new_default_for_profile(profile: Option<&str>) -> #builder_struct_name {
                 #builder_struct_name {
                     using: None,
                     #(#field_defaults )*
@@ -508,15 +1005,31 @@ fn declare_impl_builder_enum(
             pub fn default(self) -> #builder_struct_name {
                 Self::new_default()
             }
+            pub fn default_for_profile(self, profile: Option<&str>) -> #builder_struct_name {
+                Self::new_default_for_profile(profile)
+            }
             pub fn is_empty(&self) -> bool {
-                self.using.is_none()
+                if self.using.is_some() {
+                    return false;
+                }
+                #(#variants_not_set )*
+                true
+            }
+            pub fn set_fields(&self, prefix: &str, fields: &mut Vec<String>) {
+                if self.using.is_some() {
+                    fields.push(::app_config::join_path(prefix, #tag));
+                }
+                #(#collect_set_fields )*
             }
             pub fn try_build(self) -> Result<#struct_name, Vec<&'static str>> {
-                #(#match_variant )*
-                if let Some(using) = self.using {
-                    Err(vec!["using has invalid value"])
-                } else {
-                    Err(vec!["using is not specified"])
+                #infer_using
+                #default_using
+                match using {
+                    #(#match_variant )*
+                    None => {
+                        #(#check_set_variants )*
+                        Err(vec![#not_specified])
+                    }
                 }
             }
             pub fn combine(mut self, other: Self) -> Self {
@@ -528,9 +1041,20 @@ fn declare_impl_builder_enum(
             #(#using_functions )*
             #(#field_from_env_functions )*
             pub fn using_from_env(&mut self, prefix: &str) -> Result<(), Vec<String>> {
-                let env_name = format!("{}_using", prefix).to_uppercase();
-                self.using = std::env::var(&env_name).ok();
-                Ok(())
+                self.using_from_source("", &::app_config::source::EnvSource::new(prefix))
+            }
+            pub fn using_from_source(&mut self, path: &str, source: &dyn ::app_config::source::Source) -> Result<(), Vec<String>> {
+                let path = ::app_config::join_path(path, #tag);
+                match source.get(&path).map_err(|e| vec![e])? {
+                    Some(raw) => match raw.parse() {
+                        Ok(using) => {
+                            self.using = Some(using);
+                            Ok(())
+                        }
+                        Err(e) => Err(vec![format!("could not parse {}={}: {}", source.describe(&path), raw, e)]),
+                    },
+                    None => Ok(()),
+                }
             }
             pub fn new_from_env() -> Result<#builder_struct_name, Vec<String>> {
                 Self::new_from_env_prefixed("CONFIG")
@@ -539,58 +1063,576 @@ fn declare_impl_builder_enum(
                 Self::new_from_env()
             }
             pub fn new_from_env_prefixed(prefix: &str) -> Result<#builder_struct_name, Vec<String>> {
+                Self::new_from_source_prefixed("", &::app_config::source::EnvSource::new(prefix))
+            }
+            pub fn from_env_prefixed(self, prefix: &str) -> Result<#builder_struct_name, Vec<String>> {
+                Self::new_from_env_prefixed(prefix)
+            }
+            pub fn new_from_env_strict(prefix: &str) -> Result<#builder_struct_name, Vec<String>> {
+                let unknown = <#struct_name as AppConfig>::unknown_env_vars(prefix);
+                match Self::new_from_env_prefixed(prefix) {
+                    Ok(builder) if unknown.is_empty() => Ok(builder),
+                    Ok(_) => Err(unknown),
+                    Err(mut errors) => {
+                        errors.extend(unknown);
+                        Err(errors)
+                    }
+                }
+            }
+            pub fn from_env_strict(self, prefix: &str) -> Result<#builder_struct_name, Vec<String>> {
+                Self::new_from_env_strict(prefix)
+            }
+            pub fn new_from_source(source: &dyn ::app_config::source::Source) -> Result<#builder_struct_name, Vec<String>> {
+                Self::new_from_source_prefixed("", source)
+            }
+            pub fn from_source(self, source: &dyn ::app_config::source::Source) -> Result<#builder_struct_name, Vec<String>> {
+                Self::new_from_source(source)
+            }
+            pub fn new_from_source_prefixed(path: &str, source: &dyn ::app_config::source::Source) -> Result<#builder_struct_name, Vec<String>> {
                 let mut builder = #builder_struct_name::new();
 
                 let mut failed_fields = Vec::new();
-                if let Err(mut e) = builder.using_from_env(prefix) {
+                if let Err(mut e) = builder.using_from_source(path, source) {
                     failed_fields.append(&mut e);
                 }
-                #(#load_field_from_env )*
+                #(#load_field_from_source )*
                 if failed_fields.len() > 0 {
                     return Err(failed_fields);
                 }
                 Ok(builder)
             }
-            pub fn from_env_prefixed(self, prefix: &str) -> Result<#builder_struct_name, Vec<String>> {
-                Self::new_from_env_prefixed(prefix)
+            pub fn interpolate(mut self) -> Result<#builder_struct_name, Vec<String>> {
+                let mut values = std::collections::BTreeMap::new();
+                self.interpolation_values("", &mut values);
+                let resolved = ::app_config::interpolate::resolve(&values)?;
+                let mut errors = Vec::new();
+                self.apply_interpolated("", &resolved, &mut errors);
+                if !errors.is_empty() {
+                    return Err(errors);
+                }
+                Ok(self)
+            }
+            pub fn interpolation_values(
+                &self,
+                prefix: &str,
+                values: &mut std::collections::BTreeMap<String, String>,
+            ) {
+                if let Some(using) = &self.using {
+                    values.insert(::app_config::join_path(prefix, #tag), using.to_string());
+                }
+                #(#collect_interpolation_values )*
+            }
+            pub fn apply_interpolated(
+                &mut self,
+                prefix: &str,
+                resolved: &std::collections::BTreeMap<String, String>,
+                errors: &mut Vec<String>,
+            ) {
+                #(#apply_interpolated_fields )*
             }
         }
     }
 }
 
-fn get_builder_derives(attrs: &Vec<Attribute>) -> Option<TokenStream> {
-    if let Some(attr) = attrs
+/// The index of the variant that is selected by default, from `#[config_field(default)]` on
+/// the variant.
+fn default_variant(data: &syn::DataEnum) -> Option<usize> {
+    let mut defaults = data
+        .variants
         .iter()
-        .find(|attr| attr.path.is_ident("builder_derive"))
-    {
-        let tokens = &attr.tokens;
-        let derives = quote! {
-            #[derive #tokens]
-        };
-        Some(derives)
+        .enumerate()
+        .filter(|(_, variant)| has_flag(&attr_args(&variant.attrs, "config_field"), "default"))
+        .map(|(index, _)| index);
+    let default = defaults.next();
+    if defaults.next().is_some() {
+        panic!("only one variant can be the default");
+    }
+    default
+}
+
+/// The name of the field that selects the variant of an enum config, from
+/// `#[app_config(tag = "backend")]`, or `using`.
+fn enum_tag(attrs: &[Attribute]) -> String {
+    match find_value(&attr_args(attrs, "app_config"), "tag") {
+        None => "using".to_string(),
+        Some(Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(tag),
+            ..
+        })) => tag.value(),
+        Some(_) => panic!("expected a string like `tag = \"backend\"`"),
+    }
+}
+
+/// Declares the struct that builders of `#[app_config(externally_tagged)]` enum configs are
+/// deserialized from, where the only variant in a file selects it when the tag isn't set,
+/// like `postgres: {host: db}`.
+fn declare_impl_tagged(
+    builder_struct_name: &Ident,
+    tagged_name: &Ident,
+    variants: &[(Ident, Field, Ident, String)],
+    choice_name: &Ident,
+    tag: &str,
+) -> TokenStream {
+    let declare_fields = variants.iter().map(|(_, wrapped, field, name)| {
+        let ty = &wrapped.ty;
+        quote! {
+            #[serde(rename = #name)]
+            #field: Option<<#ty as AppConfig>::Builder>,
+        }
+    });
+    let present = variants.iter().map(|(variant, _, field, _)| {
+        quote! {
+            if tagged.#field.is_some() {
+                present.push(#choice_name::#variant);
+            }
+        }
+    });
+    let assign_fields = variants.iter().map(|(_, wrapped, field, _)| {
+        let ty = &wrapped.ty;
+        quote! {
+            #field: tagged.#field.unwrap_or_else(<#ty as AppConfig>::Builder::new),
+        }
+    });
+    quote! {
+        #[doc(hidden)]
+        #[derive(Default, ::serde::Deserialize)]
+        #[serde(default)]
+        struct #tagged_name {
+            #[serde(rename = #tag)]
+            using: Option<#choice_name>,
+            #(#declare_fields )*
+        }
+        impl From<#tagged_name> for #builder_struct_name {
+            fn from(tagged: #tagged_name) -> Self {
+                let mut present = Vec::new();
+                #(#present )*
+                let using = match (tagged.using, present.as_slice()) {
+                    (Some(using), _) => Some(using),
+                    (None, [choice]) => Some(*choice),
+                    (None, _) => None,
+                };
+                #builder_struct_name {
+                    using,
+                    #(#assign_fields )*
+                }
+            }
+        }
+    }
+}
+
+/// Declares the choices of an enum config, which are the values of `using`.
+fn declare_impl_choice(
+    struct_name: &Ident,
+    choice_name: &Ident,
+    variants: &[(Ident, Field, Ident, String)],
+    attrs: &[Attribute],
+    vis: &Visibility,
+) -> TokenStream {
+    let choices = variants.iter().map(|(variant, _, _, _)| variant);
+    let all = variants
+        .iter()
+        .map(|(variant, _, _, _)| quote! { #choice_name::#variant });
+    let names = variants.iter().map(|(variant, _, _, name)| {
+        quote! { #choice_name::#variant => #name, }
+    });
+    let expected = format!(
+        "expected one of {}",
+        variants
+            .iter()
+            .map(|(_, _, _, name)| format!("`{}`", name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let serialize = if derives_trait(attrs, "Serialize") {
+        quote! {
+            impl ::serde::Serialize for #choice_name {
+                fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.serialize_str(self.name())
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+    let deserialize = if derives_trait(attrs, "Deserialize") {
+        quote! {
+            impl<'de> ::serde::Deserialize<'de> for #choice_name {
+                fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let raw = <String as ::serde::Deserialize>::deserialize(deserializer)?;
+                    raw.parse().map_err(|e| {
+                        <D::Error as ::serde::de::Error>::custom(format!("could not parse {:?}: {}", raw, e))
+                    })
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+    quote! {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        #vis enum #choice_name {
+            #(#choices, )*
+        }
+        #[allow(dead_code)]
+        impl #choice_name {
+            /// All the choices, in the order of the variants.
+            pub const ALL: &'static [#choice_name] = &[#(#all, )*];
+
+            /// The name of the choice in sources.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(#names )*
+                }
+            }
+        }
+        impl std::fmt::Display for #choice_name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+        impl std::str::FromStr for #choice_name {
+            type Err = String;
+
+            fn from_str(value: &str) -> Result<Self, String> {
+                Self::ALL
+                    .iter()
+                    .find(|choice| choice.name() == value)
+                    .copied()
+                    .ok_or_else(|| #expected.to_string())
+            }
+        }
+        #serialize
+        #deserialize
+        impl ::app_config::AppConfigChoice for #struct_name {
+            type Choices = #choice_name;
+        }
+    }
+}
+
+fn named_fields(data: &syn::DataStruct) -> &Punctuated<Field, Comma> {
+    match &data.fields {
+        Fields::Named(fields) => &fields.named,
+        _ => unimplemented!(),
+    }
+}
+
+/// The variants of an enum config, with their config type, the field of the variant in the
+/// builder, and the name of the variant in sources.
+fn enum_variants(data: &syn::DataEnum, attrs: &[Attribute]) -> Vec<(Ident, Field, Ident, String)> {
+    let rename_all = RenameRule::from_attrs(attrs);
+    data.variants
+        .iter()
+        .map(|variant| {
+            let words = get_pascal_case_words(&variant.ident.to_string())
+                .expect("should be valid PascalCase");
+            let name = match rename_value(&variant.attrs) {
+                Some(name) => name,
+                None => rename_all
+                    .map(|rule| rule.apply_to_variant(&words))
+                    .unwrap_or_else(|| words_to_snake_case(words.clone())),
+            };
+            (
+                variant.ident.clone(),
+                match &variant.fields {
+                    Fields::Unnamed(fields) => {
+                        if fields.unnamed.len() != 1 {
+                            panic!(
+                                "expected each enum variant to have one field, {} has {}",
+                                variant.ident,
+                                fields.unnamed.len()
+                            );
+                        }
+                        fields.unnamed.first().unwrap().clone()
+                    }
+                    _ => unimplemented!(),
+                },
+                format_ident!("{}", words_to_snake_case(words)),
+                name,
+            )
+        })
+        .collect()
+}
+
+/// The name of a field in sources, from `#[config_field(rename = "...")]` or the
+/// `#[app_config(rename_all = "...")]` of the config.
+fn field_name(field: &Field, rename_all: Option<RenameRule>) -> String {
+    let name = field.ident.as_ref().unwrap().to_string();
+    match rename_value(&field.attrs) {
+        Some(rename) => rename,
+        None => rename_all
+            .map(|rule| rule.apply_to_field(&name))
+            .unwrap_or(name),
+    }
+}
+
+fn rename_value(attrs: &[Attribute]) -> Option<String> {
+    match find_value(&attr_args(attrs, "config_field"), "rename")? {
+        Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(name),
+            ..
+        }) => Some(name.value()),
+        _ => panic!("expected a string like `rename = \"name\"`"),
+    }
+}
+
+// Renames a field of a builder that derives `Deserialize` to its name in sources
+fn serde_rename(deserialize: bool, ident: &str, name: &str) -> TokenStream {
+    if deserialize && ident != name {
+        quote! { #[serde(rename = #name)] }
     } else {
+        quote! {}
+    }
+}
+
+/// The derives from all `#[builder_derive(...)]` attributes.
+fn get_builder_derives(attrs: &[Attribute]) -> Option<TokenStream> {
+    let derives: Vec<_> = attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("builder_derive"))
+        .map(|attr| {
+            let tokens = &attr.tokens;
+            quote! {
+                #[derive #tokens]
+            }
+        })
+        .collect();
+    if derives.is_empty() {
         None
+    } else {
+        Some(quote! { #(#derives )* })
     }
 }
 
-fn default_field_value(field: &Field) -> Option<Lit> {
-    field
-        .attrs
+/// The attributes from `#[builder_attr(...)]`, which are added to the builder or its fields as
+/// they are, like `#[builder_attr(serde(deny_unknown_fields))]`.
+fn builder_attrs(attrs: &[Attribute]) -> TokenStream {
+    let attrs = attrs
         .iter()
-        .filter(|attr| attr.path.is_ident("config_field"))
-        .filter_map(|attr| attr.parse_args::<syn::MetaNameValue>().ok())
-        .find_map(|meta| {
-            if meta.path.is_ident("default") {
-                Some(meta.lit)
-            } else {
-                None
+        .filter(|attr| attr.path.is_ident("builder_attr"))
+        .map(|attr| {
+            let tokens: TokenStream = attr
+                .parse_args()
+                .unwrap_or_else(|e| panic!("could not parse #[builder_attr(...)]: {}", e));
+            quote! { #[#tokens] }
+        });
+    quote! { #(#attrs )* }
+}
+
+/// Whether the builder derives the trait `name` with `#[builder_derive(...)]`, written as
+/// `Deserialize` or as a path like `serde::Deserialize`.
+fn derives_trait(attrs: &[Attribute], name: &str) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("builder_derive"))
+        .flat_map(|attr| {
+            attr.parse_args_with(Punctuated::<syn::Path, Comma>::parse_terminated)
+                .unwrap_or_else(|e| panic!("could not parse #[builder_derive(...)]: {}", e))
+        })
+        .any(|path| {
+            path.segments
+                .last()
+                .map(|segment| segment.ident == name)
+                .unwrap_or(false)
+        })
+}
+
+// Missing fields, nested configs and enum variants are deserialized as they are in an empty
+// builder, so any subset of the keys can be deserialized
+fn serde_default(builder_struct_name: &Ident, deserialize: bool) -> TokenStream {
+    if deserialize {
+        let new = format!("{}::new", builder_struct_name);
+        quote! { #[serde(default = #new)] }
+    } else {
+        quote! {}
+    }
+}
+
+fn default_field_value(field: &Field) -> Option<Expr> {
+    find_value(&attr_args(&field.attrs, "config_field"), "default").cloned()
+}
+
+/// The parser from `#[config_field(parse = "duration")]` or `#[config_field(parse_with = f)]`,
+/// used instead of `FromStr`.
+fn field_parser(field: &Field) -> Option<TokenStream> {
+    let args = attr_args(&field.attrs, "config_field");
+    if let Some(parser) = find_value(&args, "parse_with") {
+        return Some(quote! { #parser });
+    }
+    let parser = format_ident!("{}", field_parser_name(field)?);
+    Some(quote! { ::app_config::parse::#parser })
+}
+
+/// The name of the parser from `#[config_field(parse = "duration")]`.
+fn field_parser_name(field: &Field) -> Option<String> {
+    let name = match find_value(&attr_args(&field.attrs, "config_field"), "parse")? {
+        Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(name),
+            ..
+        }) => name.value(),
+        _ => panic!("expected the name of a parser, like `parse = \"duration\"`"),
+    };
+    match name.as_str() {
+        "duration" | "bytes" | "bool" | "percent" => Some(name),
+        _ => panic!(
+            "unknown parser `{}`, expected `duration`, `bytes`, `bool` or `percent`, \
+             or a function with `parse_with = path::to_fn`",
+            name
+        ),
+    }
+}
+
+/// Parses `raw`, the raw value of a field of type `ty` from a source, with the parser of the
+/// field or with `FromStr`, into a `Result` with the error as a string, or `None` for a
+/// `FromStr` error that doesn't implement `Display`.
+fn field_parse(field: &Field, ty: &Type) -> TokenStream {
+    match field_parser(field) {
+        Some(parser) => quote! { (#parser)(&raw).map_err(|e| Some(e.to_string())) },
+        None => quote! {{
+            #[allow(unused_imports)]
+            use ::app_config::parse::{DisplayError, OpaqueError};
+            (&::app_config::parse::FromStrValue::<#ty>::new()).parse_value(&raw)
+        }},
+    }
+}
+
+fn deserialize_fn_name(builder_struct_name: &Ident, field: &Field) -> Ident {
+    format_ident!(
+        "__{}_deserialize_{}",
+        builder_struct_name,
+        field.ident.as_ref().unwrap()
+    )
+}
+
+/// `fn migrations()` from
+/// `#[app_config(version = 3, unversioned = 1, migrate(1 = v1_to_v2, 2 = v2_to_v3))]`.
+fn migrations(attrs: &[Attribute]) -> TokenStream {
+    let args = attr_args(attrs, "app_config");
+    let migrations = find_list(&args, "migrate").unwrap_or_default();
+    let unversioned = find_value(&args, "unversioned");
+    let version = match find_value(&args, "version") {
+        Some(version) => version,
+        None if migrations.is_empty() && unversioned.is_none() => return quote! {},
+        None if unversioned.is_some() => {
+            panic!("`unversioned` requires the current version, like `version = 2`")
+        }
+        None => panic!("`migrate(...)` requires the current version, like `version = 2`"),
+    };
+    let unversioned = match unversioned {
+        Some(unversioned) => quote! { Some(#unversioned) },
+        None => quote! { None },
+    };
+    // migrations run on yaml values, so they're only available with the `yaml` feature
+    if cfg!(not(feature = "yaml")) {
+        return quote! {
+            compile_error!("versioned configs require the `yaml` feature of app-config");
+        };
+    }
+    let migrations = migrations.iter().map(|arg| match arg {
+        AttrArg::Indexed(from, migration) => {
+            quote! { (#from, #migration as ::app_config::migrate::Migration) }
+        }
+        _ => panic!(
+            "expected migrations like `migrate(1 = v1_to_v2)`, found `{}`",
+            arg.name()
+        ),
+    });
+    quote! {
+        fn migrations() -> ::app_config::migrate::Migrations {
+            ::app_config::migrate::Migrations {
+                version: Some(#version),
+                unversioned: #unversioned,
+                migrations: vec![#(#migrations ),*],
             }
+        }
+    }
+}
+
+/// The old names of a field from `#[config_field(alias = "old_name")]`, which can be repeated.
+fn field_aliases(field: &Field) -> Vec<Expr> {
+    attr_args(&field.attrs, "config_field")
+        .into_iter()
+        .filter_map(|arg| match arg {
+            AttrArg::Value(name, value) if name == "alias" => Some(*value),
+            _ => None,
         })
+        .collect()
 }
 
+/// Renames from `#[app_config(alias(old_name = "new.path"))]` on a struct, which read the old
+/// name, relative to the struct, into the field at the new path.
+fn struct_aliases(attrs: &[Attribute]) -> Vec<(Ident, Expr)> {
+    let args = attr_args(attrs, "app_config");
+    find_list(&args, "alias")
+        .unwrap_or_default()
+        .iter()
+        .map(|arg| match arg {
+            AttrArg::Value(old, new) => (old.clone(), new.as_ref().clone()),
+            _ => panic!(
+                "expected renames like `alias({} = \"new.path\")`",
+                arg.name()
+            ),
+        })
+        .collect()
+}
+
+fn profile_default_values(field: &Field) -> Vec<(Ident, Expr)> {
+    let args = attr_args(&field.attrs, "config_field");
+    find_list(&args, "profile")
+        .unwrap_or_default()
+        .iter()
+        .map(|arg| match arg {
+            AttrArg::Value(profile, value) => (profile.clone(), value.as_ref().clone()),
+            _ => panic!(
+                "expected profile defaults like `profile({} = ...)`",
+                arg.name()
+            ),
+        })
+        .collect()
+}
+
+/// The kinds of `#[nested_field]` fields, with the type of the nested config.
 enum NestedField {
-    Nested,
-    NestedOptional(Type),
+    /// `Config`, or a pointer to it like `Arc<Config>`
+    Nested(Box<Type>),
+    /// `Option<Config>`
+    NestedOptional(Box<Type>),
+    /// A pointer like `Option<Box<Config>>`, where the builder is only allocated when it's
+    /// used, so configs can contain themselves
+    NestedPointer(Box<Type>),
+}
+
+/// The type of an `Option<Config>` or `Option<Box<Config>>` nested field, and whether it's a
+/// pointer. Their builders are unset, cleared or set like optional fields.
+fn optional_nested_field(field: &Field) -> Option<(Type, bool)> {
+    match is_nested_field(field)? {
+        NestedField::Nested(_) => None,
+        NestedField::NestedOptional(ty) => Some((*ty, false)),
+        NestedField::NestedPointer(ty) => Some((*ty, true)),
+    }
+}
+
+fn boxed(pointer: bool, value: TokenStream) -> TokenStream {
+    if pointer {
+        quote! { Box::new(#value) }
+    } else {
+        value
+    }
+}
+
+fn unboxed(pointer: bool, value: TokenStream) -> TokenStream {
+    if pointer {
+        quote! { (*#value) }
+    } else {
+        value
+    }
+}
+
+fn clear_on_empty(field: &Field) -> bool {
+    let clear = has_flag(&attr_args(&field.attrs, "config_field"), "clear_on_empty");
+    if clear && matches!(is_nested_field(field), Some(NestedField::Nested(_))) {
+        panic!("`clear_on_empty` is only supported on optional nested fields");
+    }
+    clear
 }
 
 fn is_nested_field(field: &Field) -> Option<NestedField> {
@@ -599,30 +1641,90 @@ fn is_nested_field(field: &Field) -> Option<NestedField> {
         .iter()
         .find(|attr| attr.path.is_ident("nested_field"))
         .map(|_| match is_optional_field(field) {
-            Some(ty) => NestedField::NestedOptional(ty),
-            None => NestedField::Nested,
+            Some(ty) => match pointer_target(&ty) {
+                Some(ty) => NestedField::NestedPointer(Box::new(ty)),
+                None => NestedField::NestedOptional(Box::new(ty)),
+            },
+            None => NestedField::Nested(Box::new(
+                pointer_target(&field.ty).unwrap_or_else(|| field.ty.clone()),
+            )),
         })
 }
 
+/// Returns `T` for the pointers `Box<T>`, `Arc<T>` and `Rc<T>`.
+fn pointer_target(ty: &Type) -> Option<Type> {
+    let segment = match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => type_path.path.segments.last()?,
+        _ => return None,
+    };
+    if !["Box", "Arc", "Rc"]
+        .iter()
+        .any(|name| segment.ident == name)
+    {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => {
+            match args.args.first() {
+                Some(syn::GenericArgument::Type(ty)) => Some(ty.clone()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Replaces `Self` in the types of fields with the name of the config, since the generated
+/// builder uses the types where `Self` would be the builder.
+struct ReplaceSelf<'a>(&'a Ident);
+
+impl Fold for ReplaceSelf<'_> {
+    fn fold_type_path(&mut self, type_path: syn::TypePath) -> syn::TypePath {
+        if type_path.qself.is_none() && type_path.path.is_ident("Self") {
+            let name = self.0;
+            syn::parse_quote! { #name }
+        } else {
+            syn::fold::fold_type_path(self, type_path)
+        }
+    }
+}
+
+/// The type inside an optional field, which is `Option<T>`, `std::option::Option<T>` or
+/// `core::option::Option<T>`. Other types can be made optional with
+/// `#[config_field(optional)]`, like type aliases for `Option`, and `Option` fields can be made
+/// required with `#[config_field(required)]`.
 fn is_optional_field(field: &Field) -> Option<Type> {
-    match &field.ty {
-        Type::Path(type_path) => {
-            match type_path
-                .path
-                .segments
-                .first()
-                .map(|s| (&s.ident, &s.arguments))
-            {
-                Some((
-                    ident,
-                    syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments {
-                        args,
-                        ..
-                    }),
-                )) if ident == "Option" => match args.first() {
-                    Some(syn::GenericArgument::Type(ty)) => Some(ty.clone()),
-                    _ => None,
-                },
+    let args = attr_args(&field.attrs, "config_field");
+    match (has_flag(&args, "optional"), has_flag(&args, "required")) {
+        (true, true) => panic!("a field can't be both `optional` and `required`"),
+        (false, true) => None,
+        (optional, false) => option_type(&field.ty).or_else(|| {
+            let ty = &field.ty;
+            optional.then(|| parse_quote! { <#ty as ::app_config::OptionalField>::Value })
+        }),
+    }
+}
+
+fn option_type(ty: &Type) -> Option<Type> {
+    let type_path = match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => type_path,
+        _ => return None,
+    };
+    let segments: Vec<_> = type_path.path.segments.iter().collect();
+    let (last, module) = segments.split_last()?;
+    let module: Vec<String> = module.iter().map(|s| s.ident.to_string()).collect();
+    let is_option_module = match module.as_slice() {
+        [] => type_path.path.leading_colon.is_none(),
+        [krate, option] => (krate == "std" || krate == "core") && option == "option",
+        _ => false,
+    };
+    if last.ident != "Option" || !is_option_module {
+        return None;
+    }
+    match &last.arguments {
+        syn::PathArguments::AngleBracketed(arguments) if arguments.args.len() == 1 => {
+            match arguments.args.first() {
+                Some(syn::GenericArgument::Type(ty)) => Some(ty.clone()),
                 _ => None,
             }
         }
@@ -630,6 +1732,38 @@ fn is_optional_field(field: &Field) -> Option<Type> {
     }
 }
 
+/// Whether the type is a standard collection, which is cleared by setting it to an empty
+/// collection.
+fn is_collection_type(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map(|s| {
+                [
+                    "Vec", "VecDeque", "HashMap", "BTreeMap", "HashSet", "BTreeSet",
+                ]
+                .iter()
+                .any(|name| s.ident == name)
+            })
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+fn is_string_type(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map(|s| s.ident == "String" && s.arguments.is_empty())
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
 lazy_static! {
     static ref REVERSED_PASCAL_CASE_REGEX: Regex =
         Regex::new(r"([a-z]+[A-Z]|[A-Z][A-Z]*|[0-9]+)").unwrap();
@@ -658,10 +1792,6 @@ fn words_to_snake_case(words: Vec<String>) -> String {
         .unwrap()
 }
 
-fn pascal_to_snake_case(s: &str) -> String {
-    words_to_snake_case(get_pascal_case_words(s).expect("should be valid PascalCase"))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,121 @@
+use crate::attrs::{attr_args, find_value};
+use syn::{Attribute, Expr, Lit};
+
+/// The case of field and variant names in sources, from `#[app_config(rename_all = "...")]`,
+/// with the same names as serde.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    pub fn from_attrs(attrs: &[Attribute]) -> Option<RenameRule> {
+        let rule = match find_value(&attr_args(attrs, "app_config"), "rename_all")? {
+            Expr::Lit(lit) => match &lit.lit {
+                Lit::Str(rule) => rule.value(),
+                _ => panic!("expected a string like `rename_all = \"kebab-case\"`"),
+            },
+            _ => panic!("expected a string like `rename_all = \"kebab-case\"`"),
+        };
+        Some(match rule.as_str() {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+            _ => panic!("unknown `rename_all` case `{}`", rule),
+        })
+    }
+
+    /// Renames a snake case field. Like serde, `lowercase` and `UPPERCASE` keep the
+    /// underscores.
+    pub fn apply_to_field(self, name: &str) -> String {
+        let words: Vec<String> = name.split('_').map(String::from).collect();
+        self.join(&words, "_")
+    }
+
+    /// Renames a variant from its lowercase words. `lowercase` and `UPPERCASE` join the words
+    /// without a separator.
+    pub fn apply_to_variant(self, words: &[String]) -> String {
+        self.join(words, "")
+    }
+
+    fn join(self, words: &[String], plain_separator: &str) -> String {
+        match self {
+            RenameRule::Lower => words.join(plain_separator),
+            RenameRule::Upper => words.join(plain_separator).to_uppercase(),
+            RenameRule::Pascal => words.iter().map(|word| capitalize(word)).collect(),
+            RenameRule::Camel => words
+                .iter()
+                .enumerate()
+                .map(|(i, word)| {
+                    if i == 0 {
+                        word.clone()
+                    } else {
+                        capitalize(word)
+                    }
+                })
+                .collect(),
+            RenameRule::Snake => words.join("_"),
+            RenameRule::ScreamingSnake => words.join("_").to_uppercase(),
+            RenameRule::Kebab => words.join("-"),
+            RenameRule::ScreamingKebab => words.join("-").to_uppercase(),
+        }
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rename_fields() {
+        assert_eq!(
+            RenameRule::Kebab.apply_to_field("max_connections"),
+            "max-connections"
+        );
+        assert_eq!(
+            RenameRule::Camel.apply_to_field("max_connections"),
+            "maxConnections"
+        );
+        assert_eq!(
+            RenameRule::Pascal.apply_to_field("max_connections"),
+            "MaxConnections"
+        );
+        assert_eq!(
+            RenameRule::Lower.apply_to_field("max_connections"),
+            "max_connections"
+        );
+        assert_eq!(
+            RenameRule::ScreamingKebab.apply_to_field("max_connections"),
+            "MAX-CONNECTIONS"
+        );
+    }
+
+    #[test]
+    fn rename_variants() {
+        let words = vec!["postgre".to_string(), "sql".to_string()];
+        assert_eq!(RenameRule::Lower.apply_to_variant(&words), "postgresql");
+        assert_eq!(RenameRule::Kebab.apply_to_variant(&words), "postgre-sql");
+        assert_eq!(RenameRule::Pascal.apply_to_variant(&words), "PostgreSql");
+        assert_eq!(RenameRule::Upper.apply_to_variant(&words), "POSTGRESQL");
+    }
+}
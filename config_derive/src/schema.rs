@@ -0,0 +1,239 @@
+use crate::attrs::{attr_args, doc_comment, has_flag};
+use crate::rename::RenameRule;
+use crate::{
+    default_field_value, default_variant, enum_tag, field_name, field_parser, field_parser_name,
+    is_nested_field, is_optional_field, NestedField,
+};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+use syn::{Attribute, DataEnum, Field, GenericArgument, Ident, PathArguments, Type};
+
+/// Returns an expression creating the json schema for a field of type `ty`, based on the name of
+/// the type. Types that aren't recognized accept any value.
+pub fn type_schema(ty: &Type) -> TokenStream {
+    let segment = match ty {
+        Type::Path(type_path) => type_path.path.segments.last(),
+        Type::Reference(reference) => return type_schema(&reference.elem),
+        _ => None,
+    };
+    let segment = match segment {
+        Some(segment) => segment,
+        None => return quote! { ::app_config::serde_json::json!({}) },
+    };
+    let type_args: Vec<&Type> = match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    match (segment.ident.to_string().as_str(), type_args.as_slice()) {
+        (
+            "String" | "str" | "char" | "PathBuf" | "Path" | "IpAddr" | "Ipv4Addr" | "Ipv6Addr"
+            | "SocketAddr" | "Duration",
+            [],
+        ) => quote! { ::app_config::serde_json::json!({"type": "string"}) },
+        ("bool", []) => quote! { ::app_config::serde_json::json!({"type": "boolean"}) },
+        ("u8" | "u16" | "u32" | "u64" | "u128" | "usize", []) => {
+            quote! { ::app_config::serde_json::json!({"type": "integer", "minimum": 0}) }
+        }
+        ("i8" | "i16" | "i32" | "i64" | "i128" | "isize", []) => {
+            quote! { ::app_config::serde_json::json!({"type": "integer"}) }
+        }
+        ("f32" | "f64", []) => quote! { ::app_config::serde_json::json!({"type": "number"}) },
+        ("Option" | "Box" | "Arc" | "Rc", [inner]) => type_schema(inner),
+        ("Vec" | "VecDeque" | "HashSet" | "BTreeSet", [inner]) => {
+            let items = type_schema(inner);
+            quote! { ::app_config::serde_json::json!({"type": "array", "items": #items}) }
+        }
+        ("HashMap" | "BTreeMap", [_, value]) => {
+            let values = type_schema(value);
+            quote! {
+                ::app_config::serde_json::json!({"type": "object", "additionalProperties": #values})
+            }
+        }
+        _ => quote! { ::app_config::serde_json::json!({}) },
+    }
+}
+
+// The default of a field, as json. Parsed fields use the text their parser reads, so a
+// duration is written like `60s`, and other fields are serialized.
+fn default_schema_value(field: &Field) -> TokenStream {
+    let text = if field_parser_name(field).as_deref() == Some("duration") {
+        quote! { Some(::app_config::parse::format_duration(&default)) }
+    } else if field_parser(field).is_some() {
+        quote! {{
+            use ::app_config::interpolate::{DisplayValue as _, OpaqueValue as _};
+            (&::app_config::interpolate::Value(&default)).interpolation_value()
+        }}
+    } else {
+        return quote! { (&::app_config::schema::Value(&default)).default_value() };
+    };
+    match is_optional_field(field) {
+        Some(_) => quote! {
+            default.as_ref().and_then(|default| #text).map(::app_config::serde_json::Value::from)
+        },
+        None => quote! { #text.map(::app_config::serde_json::Value::from) },
+    }
+}
+
+pub fn set_description(schema: &Ident, attrs: &[Attribute]) -> TokenStream {
+    match doc_comment(attrs) {
+        Some(description) => quote! {
+            #schema["description"] = #description.into();
+        },
+        None => quote! {},
+    }
+}
+
+pub fn impl_struct_json_schema(
+    struct_name: &Ident,
+    attrs: &[Attribute],
+    fields: &Punctuated<Field, Comma>,
+) -> TokenStream {
+    let schema = format_ident!("schema");
+    let rename_all = RenameRule::from_attrs(attrs);
+    let field_schemas = fields.iter().map(|f| {
+        let name = field_name(f, rename_all);
+        let ty = &f.ty;
+        let field_schema = format_ident!("field_schema");
+        let mut field_schema_value = match is_nested_field(f) {
+            Some(
+                NestedField::Nested(ty)
+                | NestedField::NestedOptional(ty)
+                | NestedField::NestedPointer(ty),
+            ) => quote! { <#ty>::json_schema_in(defs) },
+            // parsed fields also accept the strings their parser reads
+            None if field_parser(f).is_some() => {
+                let schema = type_schema(ty);
+                quote! { ::app_config::schema::parsed(#schema) }
+            }
+            None => type_schema(ty),
+        };
+        if is_nested_field(f).is_none() {
+            if let Some(default_value) = default_field_value(f) {
+                let default_schema_value = default_schema_value(f);
+                field_schema_value = quote! {{
+                    let mut #field_schema = #field_schema_value;
+                    let default: #ty = #default_value.into();
+                    if let Some(default) = #default_schema_value {
+                        #field_schema["default"] = default;
+                    }
+                    #field_schema
+                }};
+            }
+        }
+        if is_optional_field(f).is_some() {
+            field_schema_value = quote! { ::app_config::schema::nullable(#field_schema_value) };
+        }
+        let required = is_optional_field(f).is_none() && default_field_value(f).is_none();
+        let set_description = set_description(&field_schema, &f.attrs);
+        quote! {
+            let mut #field_schema = #field_schema_value;
+            #set_description
+            properties.insert(#name.into(), #field_schema);
+            if #required {
+                required.push(#name.into());
+            }
+        }
+    });
+    let set_description = set_description(&schema, attrs);
+    let name = struct_name.to_string();
+    quote! {
+        #[allow(clippy::useless_conversion)]
+        impl #struct_name {
+            pub fn json_schema() -> ::app_config::serde_json::Value {
+                ::app_config::schema::Defs::default().root(#name, Self::json_schema_body)
+            }
+            #[doc(hidden)]
+            pub fn json_schema_in(defs: &mut ::app_config::schema::Defs) -> ::app_config::serde_json::Value {
+                defs.schema(#name, Self::json_schema_body)
+            }
+            #[allow(unused_variables)]
+            fn json_schema_body(defs: &mut ::app_config::schema::Defs) -> ::app_config::serde_json::Value {
+                #[allow(unused_imports)]
+                use ::app_config::schema::{OpaqueValue, SerializeValue};
+                let mut properties = ::app_config::serde_json::Map::new();
+                let mut required: Vec<::app_config::serde_json::Value> = Vec::new();
+                #(#field_schemas )*
+                let mut #schema = ::app_config::serde_json::json!({
+                    "type": "object",
+                    "properties": properties,
+                });
+                if !required.is_empty() {
+                    #schema["required"] = required.into();
+                }
+                #set_description
+                #schema
+            }
+        }
+    }
+}
+
+pub fn impl_enum_json_schema(
+    struct_name: &Ident,
+    attrs: &[Attribute],
+    data: &DataEnum,
+    variants: &[(Ident, Field, Ident, String)],
+) -> TokenStream {
+    let schema = format_ident!("schema");
+    let tag = enum_tag(attrs);
+    let externally_tagged = has_flag(&attr_args(attrs, "app_config"), "externally_tagged");
+    let default = default_variant(data);
+    let variant_schemas = data.variants.iter().zip(variants).enumerate().map(
+        |(index, (variant, (_, wrapped, _, name)))| {
+            let ty = &wrapped.ty;
+            let variant_schema = format_ident!("variant_schema");
+            let set_description = set_description(&variant_schema, &variant.attrs);
+            // the default variant is selected without the tag, and the externally tagged
+            // form selects the variant with its key
+            let required = if default == Some(index) {
+                quote! {}
+            } else if externally_tagged {
+                quote! { "anyOf": [{"required": [#tag]}, {"required": [#name]}], }
+            } else {
+                quote! { "required": [#tag], }
+            };
+            quote! {{
+                let mut #variant_schema = ::app_config::serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        #tag: {"const": #name},
+                        #name: <#ty>::json_schema_in(defs),
+                    },
+                    #required
+                });
+                #set_description
+                #variant_schema
+            }}
+        },
+    );
+    let set_description = set_description(&schema, attrs);
+    let name = struct_name.to_string();
+    quote! {
+        impl #struct_name {
+            pub fn json_schema() -> ::app_config::serde_json::Value {
+                ::app_config::schema::Defs::default().root(#name, Self::json_schema_body)
+            }
+            #[doc(hidden)]
+            pub fn json_schema_in(defs: &mut ::app_config::schema::Defs) -> ::app_config::serde_json::Value {
+                defs.schema(#name, Self::json_schema_body)
+            }
+            #[allow(unused_variables)]
+            fn json_schema_body(defs: &mut ::app_config::schema::Defs) -> ::app_config::serde_json::Value {
+                let variant_schemas: Vec<::app_config::serde_json::Value> = vec![#(#variant_schemas ),*];
+                let mut #schema = ::app_config::serde_json::json!({
+                    "oneOf": variant_schemas,
+                });
+                #set_description
+                #schema
+            }
+        }
+    }
+}
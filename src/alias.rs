@@ -0,0 +1,310 @@
+use crate::info::ConfigInfo;
+use crate::source::Source;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A warning about a deprecated name that was used, or a value that was ignored, returned
+/// alongside the loaded builder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    /// The dotted path that was set, like `db_url`.
+    pub path: String,
+    /// Where the value was set, like `environment variable COOL_APP_DB_URL`.
+    pub source: String,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.source, self.message)
+    }
+}
+
+/// An old name of a field, or of a nested config, with the dotted paths from the root config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alias {
+    pub old: String,
+    pub new: String,
+    pub deprecated: Option<&'static str>,
+}
+
+impl Alias {
+    /// Maps the path of a field to the old path it can be read from, if the field is the
+    /// renamed field or is nested in it.
+    pub fn old_path(&self, path: &str) -> Option<String> {
+        if path == self.new {
+            Some(self.old.clone())
+        } else {
+            let rest = path.strip_prefix(&self.new)?.strip_prefix('.')?;
+            Some(crate::join_path(&self.old, rest))
+        }
+    }
+
+    fn message(&self) -> String {
+        match self.deprecated {
+            Some(message) => format!("`{}` is deprecated: {}", self.old, message),
+            None => format!("`{}` is renamed to `{}`", self.old, self.new),
+        }
+    }
+}
+
+/// Returns the old names from `#[config_field(alias = ...)]` and
+/// `#[app_config(alias(...))]` in the config and all nested configs.
+pub fn aliases(info: &ConfigInfo) -> Vec<Alias> {
+    let mut aliases = Vec::new();
+    info.walk(&mut |path, info| {
+        if let ConfigInfo::Struct(info) = info {
+            for (old, new) in &info.aliases {
+                aliases.push(Alias {
+                    old: crate::join_path(path, old),
+                    new: crate::join_path(path, new),
+                    deprecated: None,
+                });
+            }
+            for field in &info.fields {
+                for old in &field.aliases {
+                    aliases.push(Alias {
+                        old: crate::join_path(path, old),
+                        new: crate::join_path(path, field.name),
+                        deprecated: field.deprecated,
+                    });
+                }
+            }
+        }
+    });
+    aliases
+}
+
+/// Returns the paths of fields marked `deprecated` without an alias, with their messages.
+pub fn deprecated_fields(info: &ConfigInfo) -> Vec<(String, &'static str)> {
+    let mut fields = Vec::new();
+    info.walk(&mut |path, info| {
+        if let ConfigInfo::Struct(info) = info {
+            for field in &info.fields {
+                if let (Some(message), true) = (field.deprecated, field.aliases.is_empty()) {
+                    fields.push((crate::join_path(path, field.name), message));
+                }
+            }
+        }
+    });
+    fields
+}
+
+/// Wraps a source so fields are also read from their old names, recording a warning for every
+/// old or deprecated name that is set, and the other warnings from loading, like values of enum
+/// variants that aren't selected. Setting a field and one of its old names to different values
+/// is an error. Other sources drop these warnings, so wrap a source, even one without renamed
+/// fields, to get them.
+pub struct AliasSource<'a> {
+    source: &'a dyn Source,
+    aliases: Vec<Alias>,
+    deprecated: Vec<(String, &'static str)>,
+    warnings: RefCell<Vec<Warning>>,
+    // the old path each field was read from, for error messages
+    read_from: RefCell<BTreeMap<String, String>>,
+}
+
+impl<'a> AliasSource<'a> {
+    pub fn new(source: &'a dyn Source, info: &ConfigInfo) -> Self {
+        AliasSource {
+            source,
+            aliases: aliases(info),
+            deprecated: deprecated_fields(info),
+            warnings: RefCell::new(Vec::new()),
+            read_from: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// The warnings for the old and deprecated names read so far, and the other warnings from
+    /// loading.
+    pub fn warnings(&self) -> Vec<Warning> {
+        self.warnings.borrow().clone()
+    }
+}
+
+impl<'a> Source for AliasSource<'a> {
+    fn get(&self, path: &str) -> Result<Option<String>, String> {
+        let mut value = self.source.get(path)?;
+        if value.is_some() {
+            for (_, message) in self.deprecated.iter().filter(|(p, _)| p == path) {
+                self.warn(path, format!("`{}` is deprecated: {}", path, message));
+            }
+        }
+        for alias in &self.aliases {
+            let old_path = match alias.old_path(path) {
+                Some(old_path) => old_path,
+                None => continue,
+            };
+            let old_value = match self.source.get(&old_path)? {
+                Some(old_value) => old_value,
+                None => continue,
+            };
+            self.warn(&old_path, alias.message());
+            match &value {
+                Some(value) if value != &old_value => {
+                    return Err(format!(
+                        "{} and {} are set to different values",
+                        self.source.describe(path),
+                        self.source.describe(&old_path)
+                    ))
+                }
+                Some(_) => {}
+                None => {
+                    self.read_from
+                        .borrow_mut()
+                        .insert(path.to_string(), old_path);
+                    value = Some(old_value);
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    fn describe(&self, path: &str) -> String {
+        match self.read_from.borrow().get(path) {
+            Some(old_path) => self.source.describe(old_path),
+            None => self.source.describe(path),
+        }
+    }
+
+    fn has_prefix(&self, path: &str) -> bool {
+        let prefix = format!("{}.", path);
+        self.source.has_prefix(path)
+            || self.aliases.iter().any(|alias| {
+                alias.new.starts_with(&prefix)
+                    || alias
+                        .old_path(path)
+                        .map(|old_path| self.source.has_prefix(&old_path))
+                        .unwrap_or(false)
+            })
+    }
+
+    fn warn(&self, path: &str, message: String) {
+        self.warnings.borrow_mut().push(Warning {
+            path: path.to_string(),
+            source: self.source.describe(path),
+            message,
+        });
+    }
+}
+
+/// Moves the values of old names in a yaml value to the fields they were renamed to, returning
+/// a warning for every old or deprecated name that is set, described as set in `source`.
+/// Setting a field and one of its old names to different values is an error.
+#[cfg(feature = "yaml")]
+pub fn apply_aliases(
+    info: &ConfigInfo,
+    value: &mut serde_yaml::Value,
+    source: &str,
+) -> Result<Vec<Warning>, Vec<String>> {
+    let mut warnings = Vec::new();
+    let mut errors = Vec::new();
+    for alias in aliases(info) {
+        let old_value = match remove_path(value, &alias.old) {
+            Some(old_value) => old_value,
+            None => continue,
+        };
+        warnings.push(Warning {
+            path: alias.old.clone(),
+            source: source.to_string(),
+            message: alias.message(),
+        });
+        if let Err(path) = insert_path(value, &alias.new, old_value) {
+            errors.push(format!(
+                "`{}` and `{}` are set to different values",
+                path,
+                alias.old_path(&path).unwrap_or(path.clone())
+            ));
+        }
+    }
+    for (path, message) in deprecated_fields(info) {
+        if get_path(value, &path).is_some() {
+            warnings.push(Warning {
+                message: format!("`{}` is deprecated: {}", path, message),
+                path,
+                source: source.to_string(),
+            });
+        }
+    }
+    if errors.is_empty() {
+        Ok(warnings)
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(feature = "yaml")]
+fn get_path<'v>(value: &'v serde_yaml::Value, path: &str) -> Option<&'v serde_yaml::Value> {
+    path.split('.').try_fold(value, |value, key| value.get(key))
+}
+
+#[cfg(feature = "yaml")]
+fn remove_path(value: &mut serde_yaml::Value, path: &str) -> Option<serde_yaml::Value> {
+    let (parent, key) = match path.rsplit_once('.') {
+        Some((parent, key)) => (parent.split('.').try_fold(value, |v, k| v.get_mut(k))?, key),
+        None => (value, path),
+    };
+    parent
+        .as_mapping_mut()?
+        .remove(&serde_yaml::Value::from(key))
+}
+
+// Inserts `new` at `path`, creating mappings for missing parents. Mappings are merged with
+// existing mappings, and any other value that is already set must be equal to `new`, otherwise
+// the path of the conflicting value is returned.
+#[cfg(feature = "yaml")]
+fn insert_path(
+    value: &mut serde_yaml::Value,
+    path: &str,
+    new: serde_yaml::Value,
+) -> Result<(), String> {
+    let mut value = value;
+    let mut keys = path.split('.').peekable();
+    while let Some(key) = keys.next() {
+        if value.is_null() {
+            *value = serde_yaml::Value::Mapping(Default::default());
+        }
+        let mapping = match value.as_mapping_mut() {
+            Some(mapping) => mapping,
+            // not a mapping, left for the deserializer to report
+            None => return Ok(()),
+        };
+        let key = serde_yaml::Value::from(key);
+        if keys.peek().is_none() {
+            return match mapping.get_mut(&key) {
+                None => {
+                    mapping.insert(key, new);
+                    Ok(())
+                }
+                Some(existing) => merge(existing, new, path),
+            };
+        }
+        value = mapping.entry(key).or_insert(serde_yaml::Value::Null);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "yaml")]
+fn merge(
+    existing: &mut serde_yaml::Value,
+    new: serde_yaml::Value,
+    path: &str,
+) -> Result<(), String> {
+    match (existing, new) {
+        (serde_yaml::Value::Mapping(existing), serde_yaml::Value::Mapping(new)) => {
+            for (key, new) in new {
+                let key_path = crate::join_path(path, key.as_str().unwrap_or_default());
+                match existing.get_mut(&key) {
+                    Some(existing) => merge(existing, new, &key_path)?,
+                    None => {
+                        existing.insert(key, new);
+                    }
+                }
+            }
+            Ok(())
+        }
+        (existing, new) if *existing == new => Ok(()),
+        _ => Err(path.to_string()),
+    }
+}
@@ -0,0 +1,274 @@
+use crate::info::{is_unquoted_type, ConfigInfo, FieldInfo};
+use std::fmt::Write;
+
+/// Writes an example yaml file, with defaults where they are known and commented out
+/// placeholders for the other fields.
+pub fn example_yaml(info: &ConfigInfo) -> String {
+    let mut out = String::new();
+    write_comment(&mut out, "", info.doc());
+    write_yaml(&mut out, info, "", &mut Vec::new());
+    out
+}
+
+// Configs that contain themselves are written as a placeholder inside themselves, like fields
+// without a default. Nested configs without any values are commented out, as an empty key
+// would be null.
+fn write_yaml(
+    out: &mut String,
+    info: &ConfigInfo,
+    indent: &str,
+    ancestors: &mut Vec<&'static str>,
+) {
+    let nested_indent = format!("{}  ", indent);
+    ancestors.push(info.name());
+    match info {
+        ConfigInfo::Struct(info) => {
+            for field in &info.fields {
+                write_comment(out, indent, field.doc);
+                if let Some(nested) = field.nested {
+                    let nested = nested();
+                    if ancestors.contains(&nested.name()) {
+                        writeln!(out, "{}# {}: <{}>", indent, field.name, nested.name()).unwrap();
+                        continue;
+                    }
+                    let mut body = String::new();
+                    write_comment(&mut body, &nested_indent, nested.doc());
+                    write_yaml(&mut body, &nested, &nested_indent, ancestors);
+                    write_yaml_section(out, indent, field.name, &body);
+                } else {
+                    write_value(out, indent, field, ": ");
+                }
+            }
+        }
+        ConfigInfo::Enum(info) => {
+            let choices = info.variants.iter().map(|v| v.name).collect::<Vec<_>>();
+            writeln!(out, "{}# One of: {}", indent, choices.join(", ")).unwrap();
+            match info.default {
+                Some(default) => writeln!(out, "{}{}: {}", indent, info.tag, default).unwrap(),
+                None => writeln!(
+                    out,
+                    "{}# {}: <{}>  (required)",
+                    indent,
+                    info.tag,
+                    choices.join(" | ")
+                )
+                .unwrap(),
+            }
+            for variant in &info.variants {
+                write_comment(out, indent, variant.doc);
+                writeln!(
+                    out,
+                    "{}# Used when `{}: {}`",
+                    indent, info.tag, variant.name
+                )
+                .unwrap();
+                let mut body = String::new();
+                write_yaml(&mut body, &(variant.config)(), &nested_indent, ancestors);
+                write_yaml_section(out, indent, variant.name, &body);
+            }
+        }
+    }
+    ancestors.pop();
+}
+
+fn write_yaml_section(out: &mut String, indent: &str, key: &str, body: &str) {
+    if body.lines().all(|line| line.trim_start().starts_with('#')) {
+        writeln!(out, "{}# {}:", indent, key).unwrap();
+    } else {
+        writeln!(out, "{}{}:", indent, key).unwrap();
+    }
+    out.push_str(body);
+}
+
+/// Writes an example toml file, with defaults where they are known and commented out
+/// placeholders for the other fields.
+pub fn example_toml(info: &ConfigInfo) -> String {
+    let mut out = String::new();
+    write_comment(&mut out, "", info.doc());
+    write_toml(&mut out, info, "", &mut Vec::new());
+    out
+}
+
+fn write_toml(out: &mut String, info: &ConfigInfo, table: &str, ancestors: &mut Vec<&'static str>) {
+    let table_name = |name: &str| crate::join_path(table, name);
+    ancestors.push(info.name());
+    match info {
+        ConfigInfo::Struct(info) => {
+            // Values have to come before sub tables in toml
+            for field in info.fields.iter().filter(|f| f.nested.is_none()) {
+                write_comment(out, "", field.doc);
+                write_value(out, "", field, " = ");
+            }
+            for field in &info.fields {
+                if let Some(nested) = field.nested {
+                    let nested = nested();
+                    writeln!(out).unwrap();
+                    write_comment(out, "", field.doc);
+                    if ancestors.contains(&nested.name()) {
+                        writeln!(out, "# [{}] <{}>", table_name(field.name), nested.name())
+                            .unwrap();
+                        continue;
+                    }
+                    writeln!(out, "[{}]", table_name(field.name)).unwrap();
+                    write_comment(out, "", nested.doc());
+                    write_toml(out, &nested, &table_name(field.name), ancestors);
+                }
+            }
+        }
+        ConfigInfo::Enum(info) => {
+            let choices = info.variants.iter().map(|v| v.name).collect::<Vec<_>>();
+            writeln!(out, "# One of: {}", choices.join(", ")).unwrap();
+            match info.default {
+                Some(default) => writeln!(out, "{} = {}", info.tag, quote(default)).unwrap(),
+                None => writeln!(
+                    out,
+                    "# {} = <{}>  (required)",
+                    info.tag,
+                    choices.join(" | ")
+                )
+                .unwrap(),
+            }
+            for variant in &info.variants {
+                writeln!(out).unwrap();
+                write_comment(out, "", variant.doc);
+                writeln!(out, "# Used when `{} = \"{}\"`", info.tag, variant.name).unwrap();
+                writeln!(out, "[{}]", table_name(variant.name)).unwrap();
+                write_toml(
+                    out,
+                    &(variant.config)(),
+                    &table_name(variant.name),
+                    ancestors,
+                );
+            }
+        }
+    }
+    ancestors.pop();
+}
+
+/// Writes an example `.env` file, with defaults where they are known and commented out
+/// placeholders for the other fields.
+pub fn example_env(info: &ConfigInfo, prefix: &str) -> String {
+    let mut out = String::new();
+    write_comment(&mut out, "", info.doc());
+    write_env(&mut out, info, prefix, &mut Vec::new());
+    out
+}
+
+fn write_env(out: &mut String, info: &ConfigInfo, prefix: &str, ancestors: &mut Vec<&'static str>) {
+    let env_name = |name: &str| format!("{}_{}", prefix, name.replace('-', "_")).to_uppercase();
+    ancestors.push(info.name());
+    match info {
+        ConfigInfo::Struct(info) => {
+            for field in &info.fields {
+                write_comment(out, "", field.doc);
+                if let Some(nested) = field.nested {
+                    let nested = nested();
+                    if ancestors.contains(&nested.name()) {
+                        writeln!(out, "# {}_*=<{}>", env_name(field.name), nested.name()).unwrap();
+                        continue;
+                    }
+                    write_comment(out, "", nested.doc());
+                    write_env(out, &nested, &env_name(field.name), ancestors);
+                } else {
+                    match &field.default {
+                        Some(default) => {
+                            writeln!(out, "{}={}", env_name(field.name), default).unwrap()
+                        }
+                        None => writeln!(
+                            out,
+                            "# {}=<{}>{}",
+                            env_name(field.name),
+                            field.ty,
+                            if field.is_required() {
+                                "  (required)"
+                            } else {
+                                ""
+                            }
+                        )
+                        .unwrap(),
+                    }
+                }
+            }
+        }
+        ConfigInfo::Enum(info) => {
+            let choices = info.variants.iter().map(|v| v.name).collect::<Vec<_>>();
+            writeln!(out, "# One of: {}", choices.join(", ")).unwrap();
+            match info.default {
+                Some(default) => writeln!(out, "{}={}", env_name(info.tag), default).unwrap(),
+                None => writeln!(
+                    out,
+                    "# {}=<{}>  (required)",
+                    env_name(info.tag),
+                    choices.join(" | ")
+                )
+                .unwrap(),
+            }
+            for variant in &info.variants {
+                write_comment(out, "", variant.doc);
+                writeln!(out, "# Used when {}={}", env_name(info.tag), variant.name).unwrap();
+                write_env(out, &(variant.config)(), &env_name(variant.name), ancestors);
+            }
+        }
+    }
+    ancestors.pop();
+}
+
+fn write_value(out: &mut String, indent: &str, field: &FieldInfo, separator: &str) {
+    match &field.default {
+        Some(default) if is_unquoted_type(field.ty) => {
+            writeln!(out, "{}{}{}{}", indent, field.name, separator, default).unwrap()
+        }
+        Some(default) => writeln!(
+            out,
+            "{}{}{}{}",
+            indent,
+            field.name,
+            separator,
+            quote(default)
+        )
+        .unwrap(),
+        None => writeln!(
+            out,
+            "{}# {}{}<{}>{}",
+            indent,
+            field.name,
+            separator,
+            field.ty,
+            if field.is_required() {
+                "  (required)"
+            } else {
+                ""
+            }
+        )
+        .unwrap(),
+    }
+}
+
+// Quotes a string with the escapes that yaml double quoted strings and toml basic strings have
+// in common.
+fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => write!(quoted, "\\u{:04X}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn write_comment(out: &mut String, indent: &str, doc: Option<&str>) {
+    for line in doc.into_iter().flat_map(|doc| doc.lines()) {
+        if line.is_empty() {
+            writeln!(out, "{}#", indent).unwrap();
+        } else {
+            writeln!(out, "{}# {}", indent, line).unwrap();
+        }
+    }
+}
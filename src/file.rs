@@ -0,0 +1,403 @@
+use crate::alias::Warning;
+use crate::info::ConfigInfo;
+use crate::AppConfigBuilder;
+use serde::de::DeserializeOwned;
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// An error from loading a file, with the chain of files that included it.
+#[derive(Debug)]
+pub struct FileError {
+    /// The file that failed to load, followed by the files that included it, outermost last.
+    pub include_chain: Vec<PathBuf>,
+    pub message: String,
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.include_chain.split_first() {
+            Some((path, included_from)) => {
+                write!(f, "{}: {}", path.display(), self.message)?;
+                for path in included_from {
+                    write!(f, "\n  included from {}", path.display())?;
+                }
+                Ok(())
+            }
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for FileError {}
+
+/// Loads a builder from a yaml file. The keys `include` and `extends` can be used to load
+/// other files, either a single path or a list of paths, relative to the including file.
+/// The included files are combined with the including file, where the including file takes
+/// priority, and where later files take priority over earlier files in a list.
+///
+/// The section of the active profile under the `profiles` key is loaded like the rest of the
+/// file, with its own includes, and takes priority over the rest of the file.
+pub fn load_yaml_with_includes<B>(path: impl AsRef<Path>) -> Result<B, FileError>
+where
+    B: DeserializeOwned + AppConfigBuilder,
+{
+    load_yaml_recursive(
+        path.as_ref(),
+        None,
+        &mut Vec::new(),
+        &mut Vec::new(),
+        &mut BTreeMap::new(),
+    )
+}
+
+/// Like `load_yaml_with_includes`, but also reads fields from their old names in the config
+/// described by `info`, returning a warning for every old or deprecated name that is set.
+pub fn load_yaml_with_aliases<B>(
+    path: impl AsRef<Path>,
+    info: &ConfigInfo,
+) -> Result<(B, Vec<Warning>), FileError>
+where
+    B: DeserializeOwned + AppConfigBuilder,
+{
+    let options = LoadOptions {
+        info,
+        strict: false,
+    };
+    let mut warnings = Vec::new();
+    let builder = load_yaml_recursive(
+        path.as_ref(),
+        Some(&options),
+        &mut warnings,
+        &mut Vec::new(),
+        &mut BTreeMap::new(),
+    )?;
+    Ok((builder, warnings))
+}
+
+/// Like `load_yaml_with_aliases`, but fails if the file or any file it includes contains
+/// keys that aren't fields of the config described by `info`, suggesting the closest field
+/// for typos.
+pub fn load_yaml_strict<B>(
+    path: impl AsRef<Path>,
+    info: &ConfigInfo,
+) -> Result<(B, Vec<Warning>), FileError>
+where
+    B: DeserializeOwned + AppConfigBuilder,
+{
+    let options = LoadOptions { info, strict: true };
+    let mut warnings = Vec::new();
+    let builder = load_yaml_recursive(
+        path.as_ref(),
+        Some(&options),
+        &mut warnings,
+        &mut Vec::new(),
+        &mut BTreeMap::new(),
+    )?;
+    Ok((builder, warnings))
+}
+
+struct LoadOptions<'a> {
+    info: &'a ConfigInfo,
+    strict: bool,
+}
+
+/// Where a field was read from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Origin {
+    pub file: PathBuf,
+    /// The profile whose section of the file set the field, if it wasn't set outside of the
+    /// `profiles` key.
+    pub profile: Option<String>,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.profile {
+            Some(profile) => write!(f, "{} (profile {})", self.file.display(), profile),
+            None => write!(f, "{}", self.file.display()),
+        }
+    }
+}
+
+/// A builder loaded from multiple files, with the file each field was read from.
+pub struct Fragments<B> {
+    pub builder: B,
+    /// Where each field was read from, keyed by the dotted path of the field. Fields from
+    /// included files are attributed to the included file, and fields that are set to null
+    /// count as set.
+    pub provenance: BTreeMap<String, Origin>,
+}
+
+/// Loads and combines all files in `dir` with one of the given extensions, like a `conf.d`
+/// directory. The files are sorted by name, and later files take priority over earlier files.
+/// Every file is loaded with `load_yaml_with_includes`, and the errors from all files that
+/// failed to load are returned.
+pub fn load_yaml_dir<B>(
+    dir: impl AsRef<Path>,
+    extensions: &[&str],
+) -> Result<Fragments<B>, Vec<FileError>>
+where
+    B: DeserializeOwned + AppConfigBuilder,
+{
+    let dir = dir.as_ref();
+    let entries = std::fs::read_dir(dir).map_err(|e| {
+        vec![FileError {
+            include_chain: vec![dir.to_path_buf()],
+            message: format!("could not read directory: {}", e),
+        }]
+    })?;
+    let mut paths = Vec::new();
+    let mut errors = Vec::new();
+    for entry in entries {
+        match entry {
+            Ok(entry) => paths.push(entry.path()),
+            Err(e) => errors.push(FileError {
+                include_chain: vec![dir.to_path_buf()],
+                message: format!("could not read directory: {}", e),
+            }),
+        }
+    }
+    paths.retain(|path| {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extensions.contains(&extension))
+            .unwrap_or(false)
+    });
+    paths.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+
+    let mut fragments = Fragments {
+        builder: B::new(),
+        provenance: BTreeMap::new(),
+    };
+    for path in paths {
+        let mut origins = BTreeMap::new();
+        match load_yaml_recursive::<B>(&path, None, &mut Vec::new(), &mut Vec::new(), &mut origins)
+        {
+            Ok(fragment) => {
+                fragments.provenance.extend(origins);
+                fragments.builder = fragment.combine(fragments.builder);
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(fragments)
+}
+
+// Loads a file and the files it includes. `origins` gets where each field that is set was
+// read from, unless it already has one, so the files are visited in order of priority.
+fn load_yaml_recursive<B>(
+    path: &Path,
+    options: Option<&LoadOptions>,
+    warnings: &mut Vec<Warning>,
+    stack: &mut Vec<PathBuf>,
+    origins: &mut BTreeMap<String, Origin>,
+) -> Result<B, FileError>
+where
+    B: DeserializeOwned + AppConfigBuilder,
+{
+    let error = |message: String| FileError {
+        include_chain: vec![path.to_path_buf()],
+        message,
+    };
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| error(format!("could not read file: {}", e)))?;
+    if let Some(index) = stack.iter().position(|p| p == &canonical) {
+        let cycle = stack[index..]
+            .iter()
+            .chain(Some(&canonical))
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>();
+        return Err(error(format!("include cycle {}", cycle.join(" -> "))));
+    }
+    let content =
+        std::fs::read_to_string(path).map_err(|e| error(format!("could not read file: {}", e)))?;
+    let mut value: Value = serde_yaml::from_str(&content)
+        .map_err(|e| error(format!("could not parse file: {}", e)))?;
+    let profile = crate::profile::active_profile();
+    let mut sections = take_profiles(&mut value).map_err(error)?;
+    let section = profile
+        .as_ref()
+        .and_then(|profile| sections.remove(profile));
+
+    stack.push(canonical);
+    let result = match section {
+        // the profile's section is visited first, as it takes priority
+        Some(section) => load_value::<B>(
+            path,
+            section,
+            profile.as_deref(),
+            options,
+            warnings,
+            stack,
+            origins,
+        )
+        .and_then(|overlay| {
+            load_value(path, value, None, options, warnings, stack, origins)
+                .map(|builder| overlay.combine(builder))
+        }),
+        None => load_value(path, value, None, options, warnings, stack, origins),
+    };
+    stack.pop();
+    result
+}
+
+// Loads the content of a file, or of a profile's section of the file, and the files it
+// includes. The file must be on top of `stack`.
+fn load_value<B>(
+    path: &Path,
+    mut value: Value,
+    profile: Option<&str>,
+    options: Option<&LoadOptions>,
+    warnings: &mut Vec<Warning>,
+    stack: &mut Vec<PathBuf>,
+    origins: &mut BTreeMap<String, Origin>,
+) -> Result<B, FileError>
+where
+    B: DeserializeOwned + AppConfigBuilder,
+{
+    let error = |message: String| FileError {
+        include_chain: vec![path.to_path_buf()],
+        message,
+    };
+    let includes = take_includes(&mut value).map_err(error)?;
+    B::migrations().migrate(&mut value).map_err(error)?;
+    if let Some(options) = options {
+        let source = match profile {
+            Some(profile) => format!("file {} (profile {})", path.display(), profile),
+            None => format!("file {}", path.display()),
+        };
+        let file_warnings = crate::alias::apply_aliases(options.info, &mut value, &source)
+            .map_err(|errors| error(errors.join("; ")))?;
+        warnings.extend(file_warnings);
+        if options.strict {
+            let path = match profile {
+                Some(profile) => format!("profiles.{}", profile),
+                None => String::new(),
+            };
+            let unknown = crate::strict::unknown_keys_at(options.info, &value, &path);
+            if !unknown.is_empty() {
+                return Err(error(unknown.join("; ")));
+            }
+        }
+    }
+    let mut builder: B =
+        serde_yaml::from_value(value).map_err(|e| error(format!("could not parse file: {}", e)))?;
+    let mut fields = Vec::new();
+    builder.set_fields("", &mut fields);
+    for field in fields {
+        origins.entry(field).or_insert_with(|| Origin {
+            file: path.to_path_buf(),
+            profile: profile.map(str::to_string),
+        });
+    }
+
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    for include in includes.iter().rev() {
+        match load_yaml_recursive(&dir.join(include), options, warnings, stack, origins) {
+            Ok(included) => builder = builder.combine(included),
+            Err(mut e) => {
+                e.include_chain.push(path.to_path_buf());
+                return Err(e);
+            }
+        }
+    }
+    Ok(builder)
+}
+
+/// Reads a yaml file and upgrades it to the current version of the config with
+/// `AppConfigBuilder::migrations`, returning the upgraded file with its new `version`. Included
+/// files are not loaded, and should be upgraded separately.
+pub fn upgrade_yaml<B: AppConfigBuilder>(path: impl AsRef<Path>) -> Result<String, FileError> {
+    let path = path.as_ref();
+    let error = |message: String| FileError {
+        include_chain: vec![path.to_path_buf()],
+        message,
+    };
+    let content =
+        std::fs::read_to_string(path).map_err(|e| error(format!("could not read file: {}", e)))?;
+    let mut value: Value = serde_yaml::from_str(&content)
+        .map_err(|e| error(format!("could not parse file: {}", e)))?;
+    let mut sections = take_profiles(&mut value).map_err(error)?;
+    for section in sections.values_mut() {
+        // sections are at the version of the file, and are written without a version
+        B::migrations().migrate(section).map_err(error)?;
+    }
+    B::migrations().upgrade(&mut value).map_err(error)?;
+    if let (Value::Mapping(mapping), false) = (&mut value, sections.is_empty()) {
+        let sections = sections
+            .into_iter()
+            .map(|(profile, section)| (Value::from(profile), section))
+            .collect();
+        mapping.insert(Value::from("profiles"), Value::Mapping(sections));
+    }
+    serde_yaml::to_string(&value).map_err(|e| error(format!("could not write file: {}", e)))
+}
+
+// Removes the `profiles` key, returning the section of every profile. The sections get the
+// `version` of the file, so they are migrated with it.
+fn take_profiles(value: &mut Value) -> Result<BTreeMap<String, Value>, String> {
+    let mapping = match value {
+        Value::Mapping(mapping) => mapping,
+        _ => return Ok(BTreeMap::new()),
+    };
+    let version = mapping.get(&Value::from("version")).cloned();
+    let profiles = match mapping.remove(&Value::from("profiles")) {
+        None => return Ok(BTreeMap::new()),
+        Some(Value::Mapping(profiles)) => profiles,
+        Some(_) => return Err("expected `profiles` to be a mapping of profiles".to_string()),
+    };
+    let mut sections = BTreeMap::new();
+    for (profile, mut section) in profiles {
+        let profile = match profile {
+            Value::String(profile) => profile,
+            _ => return Err("expected the profiles in `profiles` to be strings".to_string()),
+        };
+        if section.is_null() {
+            section = Value::Mapping(Default::default());
+        }
+        match (&mut section, &version) {
+            (Value::Mapping(section), Some(version)) => {
+                section.insert(Value::from("version"), version.clone());
+            }
+            (Value::Mapping(_), None) => {}
+            _ => return Err(format!("expected `profiles.{}` to be a mapping", profile)),
+        }
+        sections.insert(profile, section);
+    }
+    Ok(sections)
+}
+
+fn take_includes(value: &mut Value) -> Result<Vec<String>, String> {
+    let mapping = match value {
+        Value::Mapping(mapping) => mapping,
+        _ => return Ok(Vec::new()),
+    };
+    let mut includes = Vec::new();
+    for key in ["extends", "include"] {
+        match mapping.remove(&Value::from(key)) {
+            None => {}
+            Some(Value::String(path)) => includes.push(path),
+            Some(Value::Sequence(paths)) => {
+                for path in paths {
+                    match path {
+                        Value::String(path) => includes.push(path),
+                        _ => return Err(format!("expected `{}` to be a list of paths", key)),
+                    }
+                }
+            }
+            Some(_) => {
+                return Err(format!(
+                    "expected `{}` to be a path or a list of paths",
+                    key
+                ))
+            }
+        }
+    }
+    Ok(includes)
+}
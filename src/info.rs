@@ -0,0 +1,142 @@
+/// A description of a config type, generated by `#[derive(AppConfig)]` and returned by
+/// `AppConfig::config_info`. Used to generate examples and documentation.
+#[derive(Debug, Clone)]
+pub enum ConfigInfo {
+    Struct(StructInfo),
+    Enum(EnumInfo),
+}
+
+#[derive(Debug, Clone)]
+pub struct StructInfo {
+    pub name: &'static str,
+    pub doc: Option<&'static str>,
+    pub fields: Vec<FieldInfo>,
+    /// Renames from `#[app_config(alias(old = "new.path"))]`, as old name and new path, both
+    /// relative to the struct.
+    pub aliases: Vec<(&'static str, &'static str)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    /// The type of the field, without the `Option` for optional fields.
+    pub ty: &'static str,
+    pub doc: Option<&'static str>,
+    pub optional: bool,
+    pub has_default: bool,
+    /// The default value, if the field has a default and its type implements `Display`.
+    pub default: Option<String>,
+    /// Defaults for specific profiles, like `default` but keyed by profile.
+    pub profile_defaults: Vec<(&'static str, Option<String>)>,
+    /// For `#[nested_field]` fields, the description of the nested config type. This is a
+    /// function so configs can contain themselves.
+    pub nested: Option<fn() -> ConfigInfo>,
+    /// Old names of the field, from `#[config_field(alias = "old_name")]`.
+    pub aliases: Vec<&'static str>,
+    /// The message from `#[config_field(deprecated = "...")]`. For fields with aliases, this
+    /// is the message for using the old names, otherwise for setting the field itself.
+    pub deprecated: Option<&'static str>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumInfo {
+    pub name: &'static str,
+    pub doc: Option<&'static str>,
+    /// The field that selects the variant, `using` unless it's set with
+    /// `#[app_config(tag = "...")]`.
+    pub tag: &'static str,
+    /// The name of the variant that is selected by default, from `#[config_field(default)]`
+    /// on the variant.
+    pub default: Option<&'static str>,
+    pub variants: Vec<VariantInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct VariantInfo {
+    /// The value of the tag that selects this variant.
+    pub name: &'static str,
+    pub doc: Option<&'static str>,
+    pub config: fn() -> ConfigInfo,
+}
+
+impl ConfigInfo {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConfigInfo::Struct(info) => info.name,
+            ConfigInfo::Enum(info) => info.name,
+        }
+    }
+
+    pub fn doc(&self) -> Option<&'static str> {
+        match self {
+            ConfigInfo::Struct(info) => info.doc,
+            ConfigInfo::Enum(info) => info.doc,
+        }
+    }
+
+    /// Calls `f` with the dotted path and description of this config and of every nested
+    /// config, depth first. Configs that contain themselves are only expanded once per path
+    /// from the root, so their deeper paths aren't visited.
+    pub fn walk(&self, f: &mut dyn FnMut(&str, &ConfigInfo)) {
+        self.walk_from("", &mut Vec::new(), f);
+    }
+
+    fn walk_from(
+        &self,
+        path: &str,
+        ancestors: &mut Vec<&'static str>,
+        f: &mut dyn FnMut(&str, &ConfigInfo),
+    ) {
+        if ancestors.contains(&self.name()) {
+            return;
+        }
+        ancestors.push(self.name());
+        f(path, self);
+        match self {
+            ConfigInfo::Struct(info) => {
+                for field in &info.fields {
+                    if let Some(nested) = field.nested {
+                        let path = crate::join_path(path, field.name);
+                        nested().walk_from(&path, ancestors, f);
+                    }
+                }
+            }
+            ConfigInfo::Enum(info) => {
+                for variant in &info.variants {
+                    let path = crate::join_path(path, variant.name);
+                    (variant.config)().walk_from(&path, ancestors, f);
+                }
+            }
+        }
+        ancestors.pop();
+    }
+}
+
+impl FieldInfo {
+    /// Fields and nested configs without a default that must be set for the config to build.
+    pub fn is_required(&self) -> bool {
+        !self.optional && !self.has_default
+    }
+}
+
+/// Returns true for types that are written without quotes in yaml and toml.
+pub fn is_unquoted_type(ty: &str) -> bool {
+    matches!(
+        ty,
+        "bool"
+            | "u8"
+            | "u16"
+            | "u32"
+            | "u64"
+            | "u128"
+            | "usize"
+            | "i8"
+            | "i16"
+            | "i32"
+            | "i64"
+            | "i128"
+            | "isize"
+            | "f32"
+            | "f64"
+    )
+}
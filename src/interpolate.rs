@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+// The builders collect the value of every field that is set into a map keyed by the
+// dotted path of the field, using `Value` to turn fields into strings. Fields with a
+// type that doesn't implement `Display` can't be referenced, but they shouldn't stop
+// the rest of the builder from being interpolated, so the generated code calls
+// `(&Value(&field)).interpolation_value()` and lets method resolution pick
+// `DisplayValue` when it applies and fall back to `OpaqueValue` when it doesn't.
+//
+// This works because the call is made on a `&Value`, with both traits in scope.
+// `DisplayValue` is implemented for `Value`, so its `&self` method takes the `&Value` as it
+// is, while `OpaqueValue` is implemented for `&Value`, so its method takes a `&&Value` and
+// is only found when method resolution adds another reference. The first match wins, and
+// the impl for `Value` only exists when its bound holds. The generated code knows the type
+// of a field but not which traits it implements, so the same pattern is used wherever it
+// does something only when a trait is implemented, like `schema::Value` and `parse::Parser`.
+pub struct Value<'a, T>(pub &'a T);
+
+pub trait DisplayValue {
+    fn interpolation_value(&self) -> Option<String>;
+}
+
+impl<T: Display> DisplayValue for Value<'_, T> {
+    fn interpolation_value(&self) -> Option<String> {
+        Some(self.0.to_string())
+    }
+}
+
+pub trait OpaqueValue {
+    fn interpolation_value(&self) -> Option<String>;
+}
+
+impl<T> OpaqueValue for &Value<'_, T> {
+    fn interpolation_value(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Resolves `${other.field}` and `${env:NAME}` references in `values`, returning the
+/// new value of every entry that contained a `$`. All unresolved references and
+/// cycles are reported, prefixed by the path of the field they were found in.
+pub fn resolve(values: &BTreeMap<String, String>) -> Result<BTreeMap<String, String>, Vec<String>> {
+    let mut resolver = Resolver {
+        values,
+        resolved: BTreeMap::new(),
+        stack: Vec::new(),
+        errors: Vec::new(),
+    };
+    for path in values.keys() {
+        resolver.resolve_path(path);
+    }
+    if !resolver.errors.is_empty() {
+        return Err(resolver.errors);
+    }
+    Ok(resolver
+        .resolved
+        .into_iter()
+        .filter(|(path, _)| values[path].contains('$'))
+        .map(|(path, value)| (path, value.unwrap()))
+        .collect())
+}
+
+struct Resolver<'a> {
+    values: &'a BTreeMap<String, String>,
+    // `None` marks a path that failed to resolve, so the error isn't reported twice
+    resolved: BTreeMap<String, Option<String>>,
+    stack: Vec<String>,
+    errors: Vec<String>,
+}
+
+impl<'a> Resolver<'a> {
+    fn resolve_path(&mut self, path: &str) -> Option<String> {
+        if let Some(resolved) = self.resolved.get(path) {
+            return resolved.clone();
+        }
+        let raw = &self.values[path];
+        self.stack.push(path.to_string());
+        let resolved = self.resolve_value(path, raw);
+        self.stack.pop();
+        self.resolved.insert(path.to_string(), resolved.clone());
+        resolved
+    }
+
+    fn resolve_value(&mut self, path: &str, raw: &str) -> Option<String> {
+        let mut result = String::new();
+        let mut ok = true;
+        let mut rest = raw;
+        while let Some(start) = rest.find('$') {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+            if rest.starts_with("$${") {
+                result.push_str("${");
+                rest = &rest[3..];
+            } else if rest.starts_with("${") {
+                let end = match rest.find('}') {
+                    Some(end) => end,
+                    None => {
+                        self.errors
+                            .push(format!("{}: unterminated reference in {:?}", path, raw));
+                        return None;
+                    }
+                };
+                match self.resolve_reference(path, &rest[2..end]) {
+                    Some(value) => result.push_str(&value),
+                    None => ok = false,
+                }
+                rest = &rest[end + 1..];
+            } else {
+                result.push('$');
+                rest = &rest[1..];
+            }
+        }
+        result.push_str(rest);
+        if ok {
+            Some(result)
+        } else {
+            None
+        }
+    }
+
+    fn resolve_reference(&mut self, path: &str, reference: &str) -> Option<String> {
+        if let Some(name) = reference.strip_prefix("env:") {
+            return match std::env::var(name) {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    self.errors.push(format!(
+                        "{}: unresolved reference ${{{}}}, environment variable {} is not set",
+                        path, reference, name
+                    ));
+                    None
+                }
+            };
+        }
+        if let Some(index) = self.stack.iter().position(|p| p == reference) {
+            let mut cycle = self.stack[index..].to_vec();
+            cycle.push(reference.to_string());
+            self.errors
+                .push(format!("{}: reference cycle {}", path, cycle.join(" -> ")));
+            // Mark every path in the cycle as failed, so the cycle is only reported once
+            for p in &self.stack[index..] {
+                self.resolved.insert(p.clone(), None);
+            }
+            return None;
+        }
+        if !self.values.contains_key(reference) {
+            self.errors.push(format!(
+                "{}: unresolved reference ${{{}}}, no field with that path is set",
+                path, reference
+            ));
+            return None;
+        }
+        self.resolve_path(reference)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn resolve_references() {
+        let resolved = resolve(&values(&[
+            ("host", "example.com"),
+            ("port", "8080"),
+            ("public_url", "https://${host}:${port}"),
+            ("nested.url", "${public_url}/api"),
+            ("escaped", "$${host} costs $5"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            resolved,
+            values(&[
+                ("public_url", "https://example.com:8080"),
+                ("nested.url", "https://example.com:8080/api"),
+                ("escaped", "${host} costs $5"),
+            ])
+        );
+    }
+
+    #[test]
+    fn resolve_env() {
+        std::env::set_var("INTERPOLATE_RESOLVE_ENV_HOME", "/home/test");
+        let resolved = resolve(&values(&[(
+            "data_dir",
+            "${env:INTERPOLATE_RESOLVE_ENV_HOME}/.app",
+        )]))
+        .unwrap();
+        assert_eq!(resolved["data_dir"], "/home/test/.app");
+    }
+
+    #[test]
+    fn resolve_errors() {
+        let errors = resolve(&values(&[
+            ("a", "${b}"),
+            ("b", "${a}"),
+            ("c", "${missing}"),
+            ("d", "${env:INTERPOLATE_RESOLVE_ERRORS_MISSING}"),
+        ]))
+        .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "b: reference cycle a -> b -> a",
+                "c: unresolved reference ${missing}, no field with that path is set",
+                "d: unresolved reference ${env:INTERPOLATE_RESOLVE_ERRORS_MISSING}, environment variable INTERPOLATE_RESOLVE_ERRORS_MISSING is not set",
+            ]
+        );
+    }
+}
@@ -1,10 +1,98 @@
 pub use config_derive::*;
+#[cfg(feature = "json-schema")]
+pub use serde_json;
+
+pub mod alias;
+pub mod example;
+#[cfg(feature = "yaml")]
+pub mod file;
+pub mod info;
+pub mod interpolate;
+#[cfg(feature = "yaml")]
+pub mod migrate;
+pub mod parse;
+pub mod profile;
+pub mod reference;
+#[cfg(feature = "json-schema")]
+pub mod schema;
+pub mod source;
+pub mod strict;
 
 pub trait AppConfig {
     type Builder;
     fn builder() -> Self::Builder;
+    fn config_info() -> info::ConfigInfo;
+
+    fn example_yaml() -> String {
+        example::example_yaml(&Self::config_info())
+    }
+    fn example_toml() -> String {
+        example::example_toml(&Self::config_info())
+    }
+    fn example_env(prefix: &str) -> String {
+        example::example_env(&Self::config_info(), prefix)
+    }
+    fn reference_markdown(env_prefix: &str) -> String {
+        reference::reference_markdown(&Self::config_info(), env_prefix)
+    }
+    fn reference_man(env_prefix: &str) -> String {
+        reference::reference_man(&Self::config_info(), env_prefix)
+    }
+    /// Returns an error for every environment variable with the prefix that doesn't set a
+    /// field, for loading the config from the environment in strict mode.
+    fn unknown_env_vars(prefix: &str) -> Vec<String> {
+        strict::unknown_env_vars(&Self::config_info(), prefix)
+    }
+}
+
+/// Implemented by the builders generated by `#[derive(AppConfig)]`, so they can be used by the
+/// generic helpers in this crate.
+pub trait AppConfigBuilder: Sized {
+    fn new() -> Self;
+    fn combine(self, other: Self) -> Self;
+    /// Adds the dotted path of every field that is set to `fields`, including fields that are
+    /// set to null.
+    fn set_fields(&self, prefix: &str, fields: &mut Vec<String>);
+    /// The version of the config and the migrations that upgrade older files to it, applied to
+    /// every file loaded with `app_config::file`.
+    #[cfg(feature = "yaml")]
+    fn migrations() -> migrate::Migrations {
+        migrate::Migrations::none()
+    }
+}
+
+/// Joins the dotted path of a field with the name of one of its nested fields.
+pub fn join_path(prefix: &str, field: &str) -> String {
+    if prefix.is_empty() {
+        field.to_string()
+    } else {
+        format!("{}.{}", prefix, field)
+    }
 }
 
+/// Implemented by enum configs, with the type of the choices for `using`, like
+/// `DatabaseConfigChoice` for `DatabaseConfig`. The choices implement `FromStr` and `Display`
+/// with the names of the variants in sources, and list all choices in `ALL`.
 pub trait AppConfigChoice {
     type Choices;
 }
+
+/// Gives the value type of `#[config_field(optional)]` fields, whose type is an alias for
+/// `Option` that the derive can't see through.
+pub trait OptionalField {
+    type Value;
+}
+
+impl<T> OptionalField for Option<T> {
+    type Value = T;
+}
+
+/// Deserializes an optional field of a builder that derives `Deserialize`, so a field that is
+/// set to null is `Some(None)`, which `combine` keeps, and a missing field stays unset.
+pub fn deserialize_optional<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de>,
+{
+    serde::Deserialize::deserialize(deserializer).map(Some)
+}
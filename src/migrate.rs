@@ -0,0 +1,80 @@
+use serde_yaml::Value;
+
+/// Upgrades a file from one version to the next, before it's deserialized into a builder.
+pub type Migration = fn(&mut Value) -> Result<(), String>;
+
+/// The version of a config and the migrations from older versions, from
+/// `#[app_config(version = 3, unversioned = 1, migrate(1 = v1_to_v2, 2 = v2_to_v3))]`.
+pub struct Migrations {
+    /// The current version, or `None` if the config isn't versioned.
+    pub version: Option<u64>,
+    /// The version of files without a `version` key, or `None` if they're at the current
+    /// version.
+    pub unversioned: Option<u64>,
+    /// The migration from each older version to the next version.
+    pub migrations: Vec<(u64, Migration)>,
+}
+
+impl Migrations {
+    /// For configs without a version, where files are loaded as they are.
+    pub fn none() -> Self {
+        Migrations {
+            version: None,
+            unversioned: None,
+            migrations: Vec::new(),
+        }
+    }
+
+    /// Removes the `version` key from `value` and runs the migrations from that version to the
+    /// current version. Files without a `version` key are at the `unversioned` version if it's
+    /// set, and otherwise at the current version, so overlays that only set a few fields don't
+    /// need one. Returns the version the file was at.
+    pub fn migrate(&self, value: &mut Value) -> Result<Option<u64>, String> {
+        let current = match self.version {
+            Some(current) => current,
+            None => return Ok(None),
+        };
+        let mapping = match value.as_mapping_mut() {
+            Some(mapping) => mapping,
+            None => return Ok(None),
+        };
+        let version = match mapping.remove(&Value::from("version")) {
+            None => self.unversioned.unwrap_or(current),
+            Some(version) => version
+                .as_u64()
+                .ok_or_else(|| "expected `version` to be a positive number".to_string())?,
+        };
+        if version > current {
+            return Err(format!(
+                "version {} is newer than the supported version {}",
+                version, current
+            ));
+        }
+        for from in version..current {
+            let migration = self
+                .migrations
+                .iter()
+                .find(|(version, _)| *version == from)
+                .map(|(_, migration)| migration)
+                .ok_or_else(|| format!("no migration from version {}", from))?;
+            migration(value)
+                .map_err(|e| format!("could not migrate from version {}: {}", from, e))?;
+        }
+        Ok(Some(version))
+    }
+
+    /// Migrates `value` like `migrate`, and sets its `version` key to the current version.
+    pub fn upgrade(&self, value: &mut Value) -> Result<(), String> {
+        let current = match (self.migrate(value)?, self.version) {
+            (Some(_), Some(current)) => current,
+            _ => return Ok(()),
+        };
+        if let Value::Mapping(mapping) = value {
+            let mut upgraded = serde_yaml::Mapping::new();
+            upgraded.insert(Value::from("version"), Value::from(current));
+            upgraded.extend(std::mem::take(mapping));
+            *mapping = upgraded;
+        }
+        Ok(())
+    }
+}
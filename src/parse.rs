@@ -0,0 +1,435 @@
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{DeserializeOwned, Deserializer, Error, MapAccess, SeqAccess, Visitor};
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Parses durations like `30s`, `5m`, `1h30m`, `1.5h` or `100ms`. The units are `ns`, `us`,
+/// `ms`, `s`, `m`, `h` and `d`.
+pub fn duration(value: &str) -> Result<Duration, String> {
+    let error = || {
+        format!(
+            "expected a duration like `30s` or `1h30m`, found `{}`",
+            value
+        )
+    };
+    let value = value.trim();
+    match value {
+        "" => return Err(error()),
+        "0" => return Ok(Duration::ZERO),
+        _ => {}
+    }
+    let mut total = Duration::ZERO;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let (number, after_number) = split_number(rest);
+        let unit_len = after_number
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(after_number.len());
+        let (unit, after_unit) = after_number.split_at(unit_len);
+        let nanos: u64 = match unit {
+            "ns" => 1,
+            "us" | "µs" => 1_000,
+            "ms" => 1_000_000,
+            "s" => 1_000_000_000,
+            "m" => 60 * 1_000_000_000,
+            "h" => 60 * 60 * 1_000_000_000,
+            "d" => 24 * 60 * 60 * 1_000_000_000,
+            _ => return Err(error()),
+        };
+        let part = match number.parse::<u64>() {
+            Ok(number) => Duration::from_nanos(number.checked_mul(nanos).ok_or_else(error)?),
+            Err(_) => {
+                let number: f64 = number.parse().map_err(|_| error())?;
+                Duration::try_from_secs_f64(number * nanos as f64 / 1e9).map_err(|_| error())?
+            }
+        };
+        total = total.checked_add(part).ok_or_else(error)?;
+        rest = after_unit.trim_start();
+    }
+    Ok(total)
+}
+
+/// Formats a duration in the largest unit that divides it, like `90m` or `1500ms`, so it can
+/// be read back by `duration`.
+pub fn format_duration(value: &Duration) -> String {
+    let nanos = value.as_nanos();
+    if nanos == 0 {
+        return "0".to_string();
+    }
+    let units: [(&str, u128); 7] = [
+        ("d", 24 * 60 * 60 * 1_000_000_000),
+        ("h", 60 * 60 * 1_000_000_000),
+        ("m", 60 * 1_000_000_000),
+        ("s", 1_000_000_000),
+        ("ms", 1_000_000),
+        ("us", 1_000),
+        ("ns", 1),
+    ];
+    let (unit, size) = units
+        .into_iter()
+        .find(|(_, size)| nanos.is_multiple_of(*size))
+        .unwrap();
+    format!("{}{}", nanos / size, unit)
+}
+
+/// Parses byte sizes like `512MiB`, `1.5GB`, `10k` or `1024`, where `k`, `M`, `G`, `T` and `P`
+/// are powers of 1000, `Ki`, `Mi`, `Gi`, `Ti` and `Pi` are powers of 1024, and the `B` is
+/// optional. Units are not case sensitive.
+pub fn bytes(value: &str) -> Result<u64, String> {
+    let error = || format!("expected a size like `512MiB` or `1GB`, found `{}`", value);
+    let (number, unit) = split_number(value.trim());
+    let unit = unit.trim().to_lowercase();
+    let unit = unit.strip_suffix('b').unwrap_or(&unit);
+    let multiplier: u64 = match unit {
+        "" => 1,
+        "k" => 1000,
+        "ki" => 1 << 10,
+        "m" => 1000_u64.pow(2),
+        "mi" => 1 << 20,
+        "g" => 1000_u64.pow(3),
+        "gi" => 1 << 30,
+        "t" => 1000_u64.pow(4),
+        "ti" => 1 << 40,
+        "p" => 1000_u64.pow(5),
+        "pi" => 1 << 50,
+        _ => return Err(error()),
+    };
+    match number.parse::<u64>() {
+        Ok(number) => number.checked_mul(multiplier).ok_or_else(error),
+        Err(_) => {
+            let number: f64 = number.parse().map_err(|_| error())?;
+            let bytes = (number * multiplier as f64).round();
+            if bytes.is_finite() && bytes >= 0.0 && bytes < u64::MAX as f64 {
+                Ok(bytes as u64)
+            } else {
+                Err(error())
+            }
+        }
+    }
+}
+
+/// Parses booleans leniently, accepting `true`, `yes`, `y`, `on` and `1`, and `false`, `no`,
+/// `n`, `off` and `0`, in any case.
+pub fn bool(value: &str) -> Result<bool, String> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "yes" | "y" | "on" | "1" => Ok(true),
+        "false" | "no" | "n" | "off" | "0" => Ok(false),
+        _ => Err(format!(
+            "expected a boolean like `true`, `yes` or `on`, found `{}`",
+            value
+        )),
+    }
+}
+
+/// Parses percentages like `50%` into a fraction like `0.5`. Values without `%` are fractions.
+pub fn percent(value: &str) -> Result<f64, String> {
+    let error = || {
+        format!(
+            "expected a percentage like `50%` or `0.5`, found `{}`",
+            value
+        )
+    };
+    let value = value.trim();
+    match value.strip_suffix('%') {
+        Some(percent) => percent
+            .trim_end()
+            .parse::<f64>()
+            .map(|percent| percent / 100.0)
+            .map_err(|_| error()),
+        None => value.parse().map_err(|_| error()),
+    }
+}
+
+// Parses fields without a parser with `FromStr`, including the error when it implements
+// `Display` and `None` otherwise, with `(&FromStrValue::<T>::new()).parse_value(raw)`. See
+// `interpolate::Value`.
+pub struct FromStrValue<T>(PhantomData<fn() -> T>);
+
+impl<T: FromStr> FromStrValue<T> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        FromStrValue(PhantomData)
+    }
+}
+
+pub trait DisplayError<T> {
+    fn parse_value(&self, value: &str) -> Result<T, Option<String>>;
+}
+
+impl<T> DisplayError<T> for FromStrValue<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    fn parse_value(&self, value: &str) -> Result<T, Option<String>> {
+        value.parse().map_err(|e: T::Err| Some(e.to_string()))
+    }
+}
+
+pub trait OpaqueError<T> {
+    fn parse_value(&self, value: &str) -> Result<T, Option<String>>;
+}
+
+impl<T: FromStr> OpaqueError<T> for &FromStrValue<T> {
+    fn parse_value(&self, value: &str) -> Result<T, Option<String>> {
+        value.parse().map_err(|_| None)
+    }
+}
+
+/// Appends the error of a parser to `message`, if it has one that can be shown.
+pub fn with_error(message: String, error: Option<String>) -> String {
+    match error {
+        Some(error) => format!("{}: {}", message, error),
+        None => message,
+    }
+}
+
+// Splits a leading number, with an optional fraction, from the rest of the value.
+fn split_number(value: &str) -> (&str, &str) {
+    let len = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    value.split_at(len)
+}
+
+/// Deserializes a field with a parser, for builders that derive `Deserialize`. The value can be
+/// a string or any other scalar, which is converted to a string first, and null is `None`. See
+/// `Parser` for fields that can also be sequences and maps.
+pub fn deserialize<'de, D, T, E>(
+    deserializer: D,
+    parse: impl Fn(&str) -> Result<T, E>,
+) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    E: fmt::Display,
+{
+    match deserializer.deserialize_any(ScalarVisitor)? {
+        Some(raw) => parse_raw(&raw, parse).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn parse_raw<T, E, Er>(raw: &str, parse: impl Fn(&str) -> Result<T, E>) -> Result<T, Er>
+where
+    E: fmt::Display,
+    Er: Error,
+{
+    parse(raw).map_err(|e| Er::custom(format!("could not parse {:?}: {}", raw, e)))
+}
+
+// Deserializes fields with a parser from sequences and maps when the type of the field
+// implements `Deserialize`, and only from scalars otherwise, with
+// `(&Parser::new(parse)).deserialize(deserializer)`. See `interpolate::Value`.
+pub struct Parser<T, E, P> {
+    parse: P,
+    marker: PhantomData<fn() -> (T, E)>,
+}
+
+impl<T, E, P: Fn(&str) -> Result<T, E>> Parser<T, E, P> {
+    pub fn new(parse: P) -> Self {
+        Parser {
+            parse,
+            marker: PhantomData,
+        }
+    }
+}
+
+pub trait DeserializeStructured<T> {
+    fn deserialize<'de, D: Deserializer<'de>>(
+        &self,
+        deserializer: D,
+    ) -> Result<Option<T>, D::Error>;
+}
+
+impl<T, E, P> DeserializeStructured<T> for Parser<T, E, P>
+where
+    T: DeserializeOwned,
+    E: fmt::Display,
+    P: Fn(&str) -> Result<T, E>,
+{
+    fn deserialize<'de, D: Deserializer<'de>>(
+        &self,
+        deserializer: D,
+    ) -> Result<Option<T>, D::Error> {
+        deserializer.deserialize_any(StructuredVisitor { parser: self })
+    }
+}
+
+pub trait DeserializeScalar<T> {
+    fn deserialize<'de, D: Deserializer<'de>>(
+        &self,
+        deserializer: D,
+    ) -> Result<Option<T>, D::Error>;
+}
+
+impl<T, E, P> DeserializeScalar<T> for &Parser<T, E, P>
+where
+    E: fmt::Display,
+    P: Fn(&str) -> Result<T, E>,
+{
+    fn deserialize<'de, D: Deserializer<'de>>(
+        &self,
+        deserializer: D,
+    ) -> Result<Option<T>, D::Error> {
+        deserialize(deserializer, &self.parse)
+    }
+}
+
+// Parses scalars, and deserializes sequences and maps with the `Deserialize` of the type.
+struct StructuredVisitor<'a, T, E, P> {
+    parser: &'a Parser<T, E, P>,
+}
+
+impl<'de, T, E, P> Visitor<'de> for StructuredVisitor<'_, T, E, P>
+where
+    T: DeserializeOwned,
+    E: fmt::Display,
+    P: Fn(&str) -> Result<T, E>,
+{
+    type Value = Option<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a string, number, boolean, sequence or map")
+    }
+
+    fn visit_str<Er: Error>(self, value: &str) -> Result<Self::Value, Er> {
+        parse_raw(value, &self.parser.parse).map(Some)
+    }
+
+    fn visit_bool<Er: Error>(self, value: bool) -> Result<Self::Value, Er> {
+        self.visit_str(&value.to_string())
+    }
+
+    fn visit_i64<Er: Error>(self, value: i64) -> Result<Self::Value, Er> {
+        self.visit_str(&value.to_string())
+    }
+
+    fn visit_u64<Er: Error>(self, value: u64) -> Result<Self::Value, Er> {
+        self.visit_str(&value.to_string())
+    }
+
+    fn visit_f64<Er: Error>(self, value: f64) -> Result<Self::Value, Er> {
+        self.visit_str(&value.to_string())
+    }
+
+    fn visit_unit<Er: Error>(self) -> Result<Self::Value, Er> {
+        Ok(None)
+    }
+
+    fn visit_none<Er: Error>(self) -> Result<Self::Value, Er> {
+        Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        T::deserialize(SeqAccessDeserializer::new(seq)).map(Some)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        T::deserialize(MapAccessDeserializer::new(map)).map(Some)
+    }
+}
+
+struct ScalarVisitor;
+
+impl<'de> Visitor<'de> for ScalarVisitor {
+    type Value = Option<String>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a string, number or boolean")
+    }
+
+    fn visit_str<E: Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(Some(value.to_string()))
+    }
+
+    fn visit_bool<E: Error>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(Some(value.to_string()))
+    }
+
+    fn visit_i64<E: Error>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(Some(value.to_string()))
+    }
+
+    fn visit_u64<E: Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(Some(value.to_string()))
+    }
+
+    fn visit_f64<E: Error>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(Some(value.to_string()))
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration() {
+        assert_eq!(duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(duration("1h 30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(duration("1.5h"), Ok(Duration::from_secs(5400)));
+        assert_eq!(duration("100ms"), Ok(Duration::from_millis(100)));
+        assert_eq!(duration("0"), Ok(Duration::ZERO));
+        assert!(duration("30").is_err());
+        assert!(duration("30x").is_err());
+        assert!(duration("").is_err());
+    }
+
+    #[test]
+    fn format_durations() {
+        for (value, text) in [
+            (Duration::from_secs(5400), "90m"),
+            (Duration::from_secs(86400), "1d"),
+            (Duration::from_millis(1500), "1500ms"),
+            (Duration::ZERO, "0"),
+        ] {
+            assert_eq!(format_duration(&value), text);
+            assert_eq!(duration(text), Ok(value));
+        }
+    }
+
+    #[test]
+    fn parse_bytes() {
+        assert_eq!(bytes("1024"), Ok(1024));
+        assert_eq!(bytes("512MiB"), Ok(512 * 1024 * 1024));
+        assert_eq!(bytes("1.5GB"), Ok(1_500_000_000));
+        assert_eq!(bytes("10k"), Ok(10_000));
+        assert_eq!(bytes("4 KiB"), Ok(4096));
+        assert!(bytes("10 apples").is_err());
+    }
+
+    #[test]
+    fn parse_bool() {
+        assert_eq!(bool("Yes"), Ok(true));
+        assert_eq!(bool("on"), Ok(true));
+        assert_eq!(bool("0"), Ok(false));
+        assert!(bool("maybe").is_err());
+    }
+
+    #[test]
+    fn parse_percent() {
+        assert_eq!(percent("50%"), Ok(0.5));
+        assert_eq!(percent("0.25"), Ok(0.25));
+        assert!(percent("half").is_err());
+    }
+}
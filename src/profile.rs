@@ -0,0 +1,57 @@
+use crate::AppConfigBuilder;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// The environment variable used to select the active profile, unless a profile is set with
+/// `set_active_profile`.
+pub const PROFILE_ENV_VAR: &str = "CONFIG_PROFILE";
+
+static ACTIVE_PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Sets the active profile, overriding `CONFIG_PROFILE`. Pass `None` to go back to reading
+/// the profile from the environment.
+pub fn set_active_profile(profile: Option<&str>) {
+    *ACTIVE_PROFILE.write().unwrap() = profile.map(String::from);
+}
+
+/// Returns the profile set with `set_active_profile`, or the value of `CONFIG_PROFILE`.
+/// This is the profile used by `new_default()` on the builders.
+pub fn active_profile() -> Option<String> {
+    ACTIVE_PROFILE
+        .read()
+        .unwrap()
+        .clone()
+        .or_else(|| std::env::var(PROFILE_ENV_VAR).ok())
+}
+
+/// A builder with overlays keyed by profile, for reading files like this:
+///
+/// ```yaml
+/// port: 8080
+/// profiles:
+///   prod:
+///     port: 80
+/// ```
+///
+/// The section for the selected profile is combined on top of the rest of the file.
+#[derive(Deserialize)]
+pub struct Profiles<B> {
+    #[serde(flatten)]
+    pub base: B,
+    #[serde(default = "HashMap::new")]
+    pub profiles: HashMap<String, B>,
+}
+
+impl<B: AppConfigBuilder> Profiles<B> {
+    pub fn select(mut self, profile: Option<&str>) -> B {
+        match profile.and_then(|profile| self.profiles.remove(profile)) {
+            Some(overlay) => overlay.combine(self.base),
+            None => self.base,
+        }
+    }
+
+    pub fn select_active(self) -> B {
+        self.select(active_profile().as_deref())
+    }
+}
@@ -0,0 +1,264 @@
+use crate::info::{ConfigInfo, FieldInfo};
+use crate::source::EnvSource;
+use std::fmt::Write;
+
+/// Writes a markdown reference of the config, with a table for the root config and each
+/// nested config, linked from the fields that contain them. Defaults for the active profile
+/// are marked as active. The tables have no columns for command line flags, secrets or
+/// reloading, as configs are only read from files and sources, fields can't be marked as
+/// secret, and nothing is reloaded.
+pub fn reference_markdown(info: &ConfigInfo, env_prefix: &str) -> String {
+    let mut out = String::new();
+    let sections = sections(info);
+    let env = EnvSource::new(env_prefix);
+    for (index, section) in sections.iter().enumerate() {
+        if index > 0 {
+            writeln!(out).unwrap();
+        }
+        writeln!(out, "<a id=\"{}\"></a>", anchor(&section.path)).unwrap();
+        if section.path.is_empty() {
+            writeln!(out, "# {}", section.info.name()).unwrap();
+        } else {
+            writeln!(out, "## `{}`: {}", section.path, section.info.name()).unwrap();
+        }
+        if let Some(doc) = section.info.doc() {
+            writeln!(out, "\n{}", doc).unwrap();
+        }
+        writeln!(out).unwrap();
+        match &section.info {
+            ConfigInfo::Struct(info) => {
+                writeln!(
+                    out,
+                    "| Path | Type | Environment variable | Default | Required | Description |"
+                )
+                .unwrap();
+                writeln!(out, "|---|---|---|---|---|---|").unwrap();
+                for field in &info.fields {
+                    let path = crate::join_path(&section.path, field.name);
+                    let (ty, env_name) = match field.nested {
+                        Some(nested) => {
+                            let nested = nested();
+                            let section = section_path(&sections, &path, &nested);
+                            (
+                                format!("[{}](#{})", nested.name(), anchor(section)),
+                                String::new(),
+                            )
+                        }
+                        None => (
+                            format!("`{}`", field.ty),
+                            format!("`{}`", env.env_name(&path)),
+                        ),
+                    };
+                    writeln!(
+                        out,
+                        "| `{}` | {} | {} | {} | {} | {} |",
+                        path,
+                        ty,
+                        env_name,
+                        default_cell(field),
+                        if field.is_required() { "yes" } else { "no" },
+                        table_cell(field.doc.unwrap_or(""))
+                    )
+                    .unwrap();
+                }
+            }
+            ConfigInfo::Enum(info) => {
+                let using = crate::join_path(&section.path, info.tag);
+                writeln!(
+                    out,
+                    "One of the following, selected by `{}` (`{}`){}:\n",
+                    using,
+                    env.env_name(&using),
+                    info.default
+                        .map(|default| format!(", `{}` by default", default))
+                        .unwrap_or_default()
+                )
+                .unwrap();
+                writeln!(out, "| `{}` | Config | Description |", info.tag).unwrap();
+                writeln!(out, "|---|---|---|").unwrap();
+                for variant in &info.variants {
+                    let path = crate::join_path(&section.path, variant.name);
+                    let config = (variant.config)();
+                    writeln!(
+                        out,
+                        "| `{}` | [{}](#{}) | {} |",
+                        variant.name,
+                        config.name(),
+                        anchor(section_path(&sections, &path, &config)),
+                        table_cell(variant.doc.unwrap_or(""))
+                    )
+                    .unwrap();
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Writes a reference of the config as a roff man page, with the same content as
+/// `reference_markdown`.
+pub fn reference_man(info: &ConfigInfo, env_prefix: &str) -> String {
+    let mut out = String::new();
+    let sections = sections(info);
+    let env = EnvSource::new(env_prefix);
+    writeln!(out, ".TH {} 5", roff(&info.name().to_uppercase())).unwrap();
+    for section in &sections {
+        if section.path.is_empty() {
+            writeln!(out, ".SH {}", roff(section.info.name())).unwrap();
+        } else {
+            writeln!(out, ".SH {}", roff(&section.path.to_uppercase())).unwrap();
+            writeln!(out, "{}", roff(section.info.name())).unwrap();
+        }
+        if let Some(doc) = section.info.doc() {
+            writeln!(out, ".PP\n{}", roff(doc)).unwrap();
+        }
+        match &section.info {
+            ConfigInfo::Struct(info) => {
+                for field in &info.fields {
+                    let path = crate::join_path(&section.path, field.name);
+                    writeln!(out, ".TP\n.B {}", roff(&path)).unwrap();
+                    match field.nested {
+                        Some(nested) => {
+                            let nested = nested();
+                            writeln!(
+                                out,
+                                "{}, see {}",
+                                roff(nested.name()),
+                                roff(&section_path(&sections, &path, &nested).to_uppercase())
+                            )
+                            .unwrap()
+                        }
+                        None => {
+                            writeln!(out, "Type: {}", roff(field.ty)).unwrap();
+                            writeln!(
+                                out,
+                                ".br\nEnvironment variable: {}",
+                                roff(&env.env_name(&path))
+                            )
+                            .unwrap();
+                            let default = default_text(field);
+                            if !default.is_empty() {
+                                writeln!(out, ".br\nDefault: {}", roff(&default)).unwrap();
+                            } else if field.is_required() {
+                                writeln!(out, ".br\nRequired").unwrap();
+                            }
+                        }
+                    }
+                    if let Some(doc) = field.doc {
+                        writeln!(out, ".br\n{}", roff(doc)).unwrap();
+                    }
+                }
+            }
+            ConfigInfo::Enum(info) => {
+                let using = crate::join_path(&section.path, info.tag);
+                writeln!(
+                    out,
+                    ".PP\nOne of the following, selected by {} ({}){}:",
+                    roff(&using),
+                    roff(&env.env_name(&using)),
+                    info.default
+                        .map(|default| format!(", {} by default", roff(default)))
+                        .unwrap_or_default()
+                )
+                .unwrap();
+                for variant in &info.variants {
+                    let path = crate::join_path(&section.path, variant.name);
+                    let config = (variant.config)();
+                    writeln!(out, ".TP\n.B {}", roff(variant.name)).unwrap();
+                    writeln!(
+                        out,
+                        "{}, see {}",
+                        roff(config.name()),
+                        roff(&section_path(&sections, &path, &config).to_uppercase())
+                    )
+                    .unwrap();
+                    if let Some(doc) = variant.doc {
+                        writeln!(out, ".br\n{}", roff(doc)).unwrap();
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+struct Section {
+    path: String,
+    info: ConfigInfo,
+}
+
+// The root config and every nested config, depth first.
+fn sections(info: &ConfigInfo) -> Vec<Section> {
+    let mut sections = Vec::new();
+    info.walk(&mut |path, info| {
+        sections.push(Section {
+            path: path.to_string(),
+            info: info.clone(),
+        })
+    });
+    sections
+}
+
+// The path of the section describing the config at `path`. Configs that contain themselves
+// only have a section where they're first expanded, so deeper paths refer to that one.
+fn section_path<'s>(sections: &'s [Section], path: &str, info: &ConfigInfo) -> &'s str {
+    sections
+        .iter()
+        .find(|section| section.path == path)
+        .or_else(|| {
+            sections
+                .iter()
+                .find(|section| section.info.name() == info.name())
+        })
+        .map(|section| section.path.as_str())
+        .unwrap_or_default()
+}
+
+fn default_text(field: &FieldInfo) -> String {
+    let mut defaults = Vec::new();
+    if let Some(default) = &field.default {
+        defaults.push(format!("`{}`", default));
+    }
+    let active = crate::profile::active_profile();
+    for (profile, default) in &field.profile_defaults {
+        if let Some(default) = default {
+            if active.as_deref() == Some(*profile) {
+                defaults.push(format!("{} (active): `{}`", profile, default));
+            } else {
+                defaults.push(format!("{}: `{}`", profile, default));
+            }
+        }
+    }
+    defaults.join(", ")
+}
+
+fn default_cell(field: &FieldInfo) -> String {
+    table_cell(&default_text(field))
+}
+
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+fn anchor(path: &str) -> String {
+    if path.is_empty() {
+        "root".to_string()
+    } else {
+        path.replace('.', "-")
+    }
+}
+
+fn roff(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('`', "")
+        .lines()
+        .map(|line| {
+            if line.starts_with('.') || line.starts_with('\'') {
+                format!("\\&{}", line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,105 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+// Includes the default value of fields with types that implement `Serialize` in the generated
+// `json_schema` functions, and skips it for other types. See `interpolate::Value`.
+pub struct Value<'a, T>(pub &'a T);
+
+pub trait SerializeValue {
+    fn default_value(&self) -> Option<serde_json::Value>;
+}
+
+impl<T: Serialize> SerializeValue for Value<'_, T> {
+    fn default_value(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.0).ok()
+    }
+}
+
+pub trait OpaqueValue {
+    fn default_value(&self) -> Option<serde_json::Value>;
+}
+
+impl<T> OpaqueValue for &Value<'_, T> {
+    fn default_value(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+/// Makes `schema` also accept null, which clears optional fields.
+pub fn nullable(schema: serde_json::Value) -> serde_json::Value {
+    also_accept(schema, "null")
+}
+
+/// Makes `schema` also accept strings, for fields with a parser, which reads strings as well
+/// as the values of the type of the field.
+pub fn parsed(schema: serde_json::Value) -> serde_json::Value {
+    also_accept(schema, "string")
+}
+
+fn also_accept(mut schema: serde_json::Value, other: &str) -> serde_json::Value {
+    let mut types = match schema.get("type") {
+        Some(serde_json::Value::String(ty)) => vec![ty.clone()],
+        Some(serde_json::Value::Array(types)) => types
+            .iter()
+            .filter_map(|ty| ty.as_str().map(String::from))
+            .collect(),
+        // schemas without a type accept anything already
+        _ if schema.as_object().is_some_and(|schema| schema.is_empty()) => return schema,
+        _ => return serde_json::json!({"anyOf": [schema, {"type": other}]}),
+    };
+    if !types.iter().any(|ty| ty == other) {
+        types.push(other.to_string());
+    }
+    schema["type"] = match types.as_slice() {
+        [ty] => serde_json::json!(ty),
+        _ => serde_json::json!(types),
+    };
+    schema
+}
+
+/// The definitions of configs that contain themselves, directly or through other configs,
+/// collected while the generated `json_schema` functions describe the nested configs.
+#[derive(Default)]
+pub struct Defs {
+    ancestors: Vec<&'static str>,
+    recursive: BTreeSet<&'static str>,
+    defs: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Defs {
+    /// Returns the schema of the config called `name`, described by `schema`. Configs that
+    /// contain themselves are described once under `$defs`, and referenced with `$ref`.
+    pub fn schema(
+        &mut self,
+        name: &'static str,
+        schema: impl FnOnce(&mut Defs) -> serde_json::Value,
+    ) -> serde_json::Value {
+        let reference = serde_json::json!({"$ref": format!("#/$defs/{}", name)});
+        if self.ancestors.contains(&name) {
+            self.recursive.insert(name);
+            return reference;
+        }
+        self.ancestors.push(name);
+        let schema = schema(self);
+        self.ancestors.pop();
+        if self.recursive.contains(name) {
+            self.defs.insert(name.to_string(), schema);
+            reference
+        } else {
+            schema
+        }
+    }
+
+    /// Like `schema`, for the root config, adding the definitions.
+    pub fn root(
+        mut self,
+        name: &'static str,
+        schema: impl FnOnce(&mut Defs) -> serde_json::Value,
+    ) -> serde_json::Value {
+        let mut schema = self.schema(name, schema);
+        if !self.defs.is_empty() {
+            schema["$defs"] = serde_json::Value::Object(self.defs);
+        }
+        schema
+    }
+}
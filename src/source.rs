@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A source of raw string values for fields, looked up by the dotted path of the field.
+/// The values are parsed with `FromStr`, like values from environment variables.
+pub trait Source {
+    /// Returns the raw value for the field at `path`, or `None` if the source doesn't have it.
+    fn get(&self, path: &str) -> Result<Option<String>, String>;
+    /// Describes where the value for `path` comes from, for error messages.
+    fn describe(&self, path: &str) -> String;
+    /// Returns true if the source has values for fields nested in `path`. Used to skip
+    /// optional nested configs, so configs that contain themselves, like `Option<Box<Self>>`,
+    /// aren't loaded forever.
+    fn has_prefix(&self, path: &str) -> bool;
+    /// Records a warning about the values for `path`, like a value for an enum variant that
+    /// isn't selected and can't be parsed. Sources that keep warnings, like `AliasSource`,
+    /// return them to the caller, and the others drop them.
+    fn warn(&self, _path: &str, _message: String) {}
+}
+
+/// Reads fields from environment variables, where `nested.field` with the prefix `CONFIG`
+/// is read from `CONFIG_NESTED_FIELD`.
+pub struct EnvSource {
+    prefix: String,
+}
+
+impl EnvSource {
+    pub fn new(prefix: &str) -> Self {
+        EnvSource {
+            prefix: prefix.to_string(),
+        }
+    }
+
+    /// The environment variable for a path, like `CONFIG_DATABASE_MAX_CONNECTIONS` for
+    /// `database.max-connections`.
+    pub fn env_name(&self, path: &str) -> String {
+        format!("{}_{}", self.prefix, path.replace(['.', '-'], "_")).to_uppercase()
+    }
+}
+
+impl Source for EnvSource {
+    fn get(&self, path: &str) -> Result<Option<String>, String> {
+        let env_name = self.env_name(path);
+        match std::env::var(&env_name) {
+            Ok(value) => Ok(Some(value)),
+            Err(std::env::VarError::NotPresent) => Ok(None),
+            Err(_) => Err(format!("could not read environment variable {}", env_name)),
+        }
+    }
+
+    fn describe(&self, path: &str) -> String {
+        format!("environment variable {}", self.env_name(path))
+    }
+
+    fn has_prefix(&self, path: &str) -> bool {
+        let prefix = format!("{}_", self.env_name(path));
+        std::env::vars_os().any(|(name, _)| {
+            name.to_str()
+                .map(|name| name.starts_with(&prefix))
+                .unwrap_or(false)
+        })
+    }
+}
+
+/// Reads fields from a directory with one file per field, like a Kubernetes ConfigMap or
+/// Secret mounted as a volume. The file name is the name of the field, where `..` or `__`
+/// separates nested fields, so `database__host` and `database/host` both set `database.host`.
+/// Trailing newlines are removed from the values, and entries starting with `..`, like the
+/// `..data` symlink Kubernetes uses for atomic updates, are ignored.
+pub struct DirSource {
+    values: BTreeMap<String, (PathBuf, String)>,
+}
+
+impl DirSource {
+    pub fn new(dir: impl AsRef<Path>) -> Result<Self, String> {
+        let mut values = BTreeMap::new();
+        read_dir_values(dir.as_ref(), "", &mut values)?;
+        Ok(DirSource { values })
+    }
+}
+
+fn read_dir_values(
+    dir: &Path,
+    prefix: &str,
+    values: &mut BTreeMap<String, (PathBuf, String)>,
+) -> Result<(), String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("could not read directory {}: {}", dir.display(), e))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| format!("could not read directory {}: {}", dir.display(), e))?
+            .path();
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if !name.starts_with("..") => name,
+            _ => continue,
+        };
+        let key =
+            crate::join_path(prefix, &name.replace("..", ".").replace("__", ".")).to_lowercase();
+        if path.is_dir() {
+            read_dir_values(&path, &key, values)?;
+        } else {
+            let value = std::fs::read_to_string(&path)
+                .map_err(|e| format!("could not read file {}: {}", path.display(), e))?;
+            let value = value.trim_end_matches(&['\n', '\r'][..]).to_string();
+            values.insert(key, (path, value));
+        }
+    }
+    Ok(())
+}
+
+// The file names are lowercased when the directory is read, so fields are matched regardless
+// of case, including fields renamed to `camelCase`.
+impl Source for DirSource {
+    fn get(&self, path: &str) -> Result<Option<String>, String> {
+        Ok(self
+            .values
+            .get(&path.to_lowercase())
+            .map(|(_, value)| value.clone()))
+    }
+
+    fn describe(&self, path: &str) -> String {
+        match self.values.get(&path.to_lowercase()) {
+            Some((file, _)) => format!("file {}", file.display()),
+            None => format!("file for {}", path),
+        }
+    }
+
+    fn has_prefix(&self, path: &str) -> bool {
+        let prefix = format!("{}.", path.to_lowercase());
+        self.values.keys().any(|key| key.starts_with(&prefix))
+    }
+}
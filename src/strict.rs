@@ -0,0 +1,264 @@
+use crate::info::{ConfigInfo, FieldInfo};
+use crate::source::EnvSource;
+
+/// Returns the dotted path of every value that can be set in the config, including the
+/// tag of enum configs, like `using`, and the old names of renamed fields.
+pub fn known_paths(info: &ConfigInfo) -> Vec<String> {
+    let mut paths = Vec::new();
+    info.walk(&mut |path, info| match info {
+        ConfigInfo::Struct(info) => paths.extend(
+            info.fields
+                .iter()
+                .filter(|field| field.nested.is_none())
+                .map(|field| crate::join_path(path, field.name)),
+        ),
+        ConfigInfo::Enum(info) => paths.push(crate::join_path(path, info.tag)),
+    });
+    let aliases = crate::alias::aliases(info);
+    let old_paths: Vec<String> = paths
+        .iter()
+        .flat_map(|path| aliases.iter().filter_map(move |alias| alias.old_path(path)))
+        .collect();
+    paths.extend(old_paths);
+    paths
+}
+
+/// Returns an error for every environment variable starting with `{prefix}_` that doesn't
+/// set a field or section of the config, like `COOL_APP_PROT` instead of `COOL_APP_PORT`.
+/// `CONFIG_PROFILE`, which selects the profile, is never reported.
+pub fn unknown_env_vars(info: &ConfigInfo, prefix: &str) -> Vec<String> {
+    let env = EnvSource::new(prefix);
+    let env_prefix = format!("{}_", prefix.to_uppercase());
+    let mut unknown: Vec<String> = std::env::vars_os()
+        .filter_map(|(name, _)| name.into_string().ok())
+        .filter(|name| name != crate::profile::PROFILE_ENV_VAR)
+        .filter(|name| match name.strip_prefix(&env_prefix) {
+            Some(rest) => !sets_env_field(info, rest),
+            None => false,
+        })
+        .collect();
+    unknown.sort();
+    // suggestions are only made from the paths that `known_paths` expands
+    let known: Vec<String> = known_paths(info)
+        .iter()
+        .map(|path| env.env_name(path))
+        .collect();
+    unknown
+        .into_iter()
+        .map(|name| {
+            let message = format!("environment variable {} does not match any field", name);
+            // compare without the prefix, which every name shares
+            let known = known.iter().map(|known| &known[env_prefix.len()..]);
+            match suggest(&name[env_prefix.len()..], known) {
+                Some(known) => format!("{}, did you mean {}{}?", message, env_prefix, known),
+                None => message,
+            }
+        })
+        .collect()
+}
+
+// Whether `name`, an environment variable without its prefix, sets a field of the config or
+// a section, like the empty value that clears a `clear_on_empty` section. The config is
+// walked along the name, so configs that contain themselves match at any depth.
+fn sets_env_field(info: &ConfigInfo, name: &str) -> bool {
+    match info {
+        ConfigInfo::Struct(info) => {
+            sets_struct_field(&info.fields, name)
+                || info
+                    .aliases
+                    .iter()
+                    .any(|(old, new)| match strip_env_segment(name, old) {
+                        Some(Some(rest)) => {
+                            let name = format!("{}_{}", env_segment(new), rest);
+                            sets_struct_field(&info.fields, &name)
+                        }
+                        Some(None) => true,
+                        None => false,
+                    })
+        }
+        ConfigInfo::Enum(info) => {
+            strip_env_segment(name, info.tag) == Some(None)
+                || info
+                    .variants
+                    .iter()
+                    .any(|variant| match strip_env_segment(name, variant.name) {
+                        Some(Some(rest)) => sets_env_field(&(variant.config)(), rest),
+                        Some(None) => true,
+                        None => false,
+                    })
+        }
+    }
+}
+
+fn sets_struct_field(fields: &[FieldInfo], name: &str) -> bool {
+    fields.iter().any(|field| {
+        std::iter::once(&field.name).chain(&field.aliases).any(
+            |field_name| match strip_env_segment(name, field_name) {
+                Some(Some(rest)) => field
+                    .nested
+                    .map(|nested| sets_env_field(&nested(), rest))
+                    .unwrap_or(false),
+                Some(None) => true,
+                None => false,
+            },
+        )
+    })
+}
+
+// Strips the environment variable name of `path` from the start of `name`, returning
+// `Some(None)` if that's all of `name` and `Some(Some(rest))` for the rest after a `_`.
+fn strip_env_segment<'n>(name: &'n str, path: &str) -> Option<Option<&'n str>> {
+    let rest = name.strip_prefix(&env_segment(path))?;
+    if rest.is_empty() {
+        Some(None)
+    } else {
+        rest.strip_prefix('_').map(Some)
+    }
+}
+
+fn env_segment(path: &str) -> String {
+    path.replace(['.', '-'], "_").to_uppercase()
+}
+
+/// Returns an error for every key in `value` that isn't a field of the config, like
+/// `postgress` instead of `postgres`. The sections under `profiles` are checked like the
+/// rest of the file.
+#[cfg(feature = "yaml")]
+pub fn unknown_keys(info: &ConfigInfo, value: &serde_yaml::Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    let profiles = serde_yaml::Value::from("profiles");
+    match value {
+        serde_yaml::Value::Mapping(mapping) if mapping.contains_key(&profiles) => {
+            let mut mapping = mapping.clone();
+            if let Some(serde_yaml::Value::Mapping(sections)) = mapping.remove(&profiles) {
+                check_keys(info, &serde_yaml::Value::Mapping(mapping), "", &mut errors);
+                for (profile, section) in &sections {
+                    if let Some(profile) = profile.as_str() {
+                        let path = format!("profiles.{}", profile);
+                        check_keys(info, section, &path, &mut errors);
+                    }
+                }
+            } else {
+                check_keys(info, value, "", &mut errors);
+            }
+        }
+        _ => check_keys(info, value, "", &mut errors),
+    }
+    errors
+}
+
+// Like `unknown_keys`, for a value at `path` in a file, like the section of a profile.
+#[cfg(feature = "yaml")]
+pub(crate) fn unknown_keys_at(
+    info: &ConfigInfo,
+    value: &serde_yaml::Value,
+    path: &str,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    check_keys(info, value, path, &mut errors);
+    errors
+}
+
+#[cfg(feature = "yaml")]
+fn check_keys(info: &ConfigInfo, value: &serde_yaml::Value, path: &str, errors: &mut Vec<String>) {
+    // values that aren't mappings are left for the deserializer to report
+    let mapping = match value {
+        serde_yaml::Value::Mapping(mapping) => mapping,
+        _ => return,
+    };
+    let known: Vec<&'static str> = match info {
+        ConfigInfo::Struct(info) => info.fields.iter().map(|field| field.name).collect(),
+        ConfigInfo::Enum(info) => std::iter::once(info.tag)
+            .chain(info.variants.iter().map(|variant| variant.name))
+            .collect(),
+    };
+    for (key, value) in mapping {
+        let key = match key.as_str() {
+            Some(key) => key,
+            None => continue,
+        };
+        let key_path = crate::join_path(path, key);
+        let nested = match info {
+            ConfigInfo::Struct(info) => info
+                .fields
+                .iter()
+                .find(|field| field.name == key)
+                .map(|field| field.nested.map(|nested| nested())),
+            ConfigInfo::Enum(info) if key == info.tag => Some(None),
+            ConfigInfo::Enum(info) => info
+                .variants
+                .iter()
+                .find(|variant| variant.name == key)
+                .map(|variant| Some((variant.config)())),
+        };
+        match nested {
+            Some(Some(nested)) => check_keys(&nested, value, &key_path, errors),
+            Some(None) => {}
+            None => match suggest(key, known.iter().copied()) {
+                Some(known) => errors.push(format!(
+                    "unknown key `{}`, did you mean `{}`?",
+                    key_path,
+                    crate::join_path(path, known)
+                )),
+                None => errors.push(format!("unknown key `{}`", key_path)),
+            },
+        }
+    }
+}
+
+/// Returns the known name closest to `name`, if it's close enough to be a typo.
+fn suggest<'a>(name: &str, known: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (name.len() / 3).max(1);
+    known
+        .map(|known| (edit_distance(name, known), known))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, known)| known)
+}
+
+/// The number of insertions, deletions, substitutions and transpositions of adjacent
+/// characters needed to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance() {
+        assert_eq!(edit_distance("postgres", "postgres"), 0);
+        assert_eq!(edit_distance("postgress", "postgres"), 1);
+        assert_eq!(edit_distance("prot", "port"), 1);
+        assert_eq!(edit_distance("sqlite", "postgres"), 7);
+    }
+
+    #[test]
+    fn suggestion() {
+        let known = ["port", "host"];
+        assert_eq!(suggest("prot", known.iter().copied()), Some("port"));
+        assert_eq!(suggest("name", known.iter().copied()), None);
+    }
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use app_config::alias::{AliasSource, Warning};
+use app_config::file::load_yaml_with_aliases;
+use app_config::source::EnvSource;
+use app_config::AppConfig;
+use common::TempDir;
+use serde::Deserialize;
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct DatabaseConfig {
+    url: String,
+    #[config_field(alias = "pool", deprecated = "use max_connections")]
+    max_connections: u32,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+#[app_config(alias(db_url = "database.url"))]
+struct ServiceConfig {
+    #[config_field(deprecated = "logs are always written to stderr")]
+    log_file: Option<String>,
+    #[nested_field]
+    database: DatabaseConfig,
+}
+
+#[test]
+fn alias_env() {
+    std::env::set_var("ALIAS_ENV_DB_URL", "postgres://db");
+    std::env::set_var("ALIAS_ENV_DATABASE_POOL", "10");
+    std::env::set_var("ALIAS_ENV_LOG_FILE", "service.log");
+    let env = EnvSource::new("ALIAS_ENV");
+    let source = AliasSource::new(&env, &ServiceConfig::config_info());
+    let config = ServiceConfig::builder()
+        .from_source(&source)
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(config.database.url, "postgres://db");
+    assert_eq!(config.database.max_connections, 10);
+    assert!(ServiceConfig::unknown_env_vars("ALIAS_ENV").is_empty());
+    assert_eq!(
+        source.warnings(),
+        vec![
+            Warning {
+                path: "log_file".to_string(),
+                source: "environment variable ALIAS_ENV_LOG_FILE".to_string(),
+                message: "`log_file` is deprecated: logs are always written to stderr".to_string(),
+            },
+            Warning {
+                path: "db_url".to_string(),
+                source: "environment variable ALIAS_ENV_DB_URL".to_string(),
+                message: "`db_url` is renamed to `database.url`".to_string(),
+            },
+            Warning {
+                path: "database.pool".to_string(),
+                source: "environment variable ALIAS_ENV_DATABASE_POOL".to_string(),
+                message: "`database.pool` is deprecated: use max_connections".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn alias_env_conflict() {
+    std::env::set_var("ALIAS_ENV_CONFLICT_DB_URL", "postgres://old");
+    std::env::set_var("ALIAS_ENV_CONFLICT_DATABASE_URL", "postgres://new");
+    std::env::set_var("ALIAS_ENV_CONFLICT_DATABASE_MAX_CONNECTIONS", "10");
+    std::env::set_var("ALIAS_ENV_CONFLICT_DATABASE_POOL", "10");
+    let env = EnvSource::new("ALIAS_ENV_CONFLICT");
+    let source = AliasSource::new(&env, &ServiceConfig::config_info());
+    assert_eq!(
+        ServiceConfig::builder().from_source(&source).err().unwrap(),
+        vec![
+            "environment variable ALIAS_ENV_CONFLICT_DATABASE_URL and \
+             environment variable ALIAS_ENV_CONFLICT_DB_URL are set to different values"
+        ]
+    );
+}
+
+#[test]
+fn alias_file() {
+    let dir = TempDir::with_files(
+        "alias_file",
+        &[("service.yml", "db_url: postgres://db\ndatabase: {pool: 10}")],
+    );
+    let (builder, warnings) = load_yaml_with_aliases::<<ServiceConfig as AppConfig>::Builder>(
+        dir.join("service.yml"),
+        &ServiceConfig::config_info(),
+    )
+    .unwrap();
+    let config = builder
+        .combine(ServiceConfig::builder())
+        .try_build()
+        .unwrap();
+    assert_eq!(config.database.url, "postgres://db");
+    assert_eq!(config.database.max_connections, 10);
+    assert_eq!(
+        warnings
+            .iter()
+            .map(|warning| warning.message.as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            "`db_url` is renamed to `database.url`",
+            "`database.pool` is deprecated: use max_connections",
+        ]
+    );
+    assert_eq!(
+        warnings[0].source,
+        format!("file {}", dir.join("service.yml").display())
+    );
+}
+
+#[test]
+fn alias_file_conflict() {
+    let dir = TempDir::with_files(
+        "alias_file_conflict",
+        &[("service.yml", "db_url: postgres://old\ndatabase: {url: postgres://new, max_connections: 10, pool: 10}")],
+    );
+    let error = load_yaml_with_aliases::<<ServiceConfig as AppConfig>::Builder>(
+        dir.join("service.yml"),
+        &ServiceConfig::config_info(),
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+        error.message,
+        "`database.url` and `db_url` are set to different values"
+    );
+}
@@ -0,0 +1,129 @@
+use app_config::AppConfig;
+use serde::Deserialize;
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct ProxyConfig {
+    url: String,
+}
+
+fn parse_list(value: &str) -> Result<Vec<String>, String> {
+    Ok(value.split(',').map(String::from).collect())
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct ClientConfig {
+    #[config_field(clear_on_empty)]
+    user_agent: Option<String>,
+    #[nested_field]
+    #[config_field(clear_on_empty)]
+    proxy: Option<ProxyConfig>,
+    #[config_field(clear_on_empty, parse_with = parse_list, default = Vec::new())]
+    hosts: Vec<String>,
+    #[config_field(parse_with = parse_list, default = Vec::new())]
+    headers: Vec<String>,
+}
+
+static BASE_YML: &str = r#"
+user_agent: client/1.0
+proxy:
+  url: http://proxy
+hosts: a,b
+headers: accept
+"#;
+
+fn base() -> <ClientConfig as AppConfig>::Builder {
+    serde_yaml::from_str(BASE_YML).unwrap()
+}
+
+#[test]
+fn clear_in_file() {
+    let overlay: <ClientConfig as AppConfig>::Builder =
+        serde_yaml::from_str("user_agent: null\nproxy: null\nhosts: null\nheaders: null").unwrap();
+    let config = overlay
+        .combine(base())
+        .combine(ClientConfig::builder().default())
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config,
+        ClientConfig {
+            user_agent: None,
+            proxy: None,
+            hosts: Vec::new(),
+            headers: Vec::new(),
+        }
+    );
+}
+
+#[test]
+fn clear_in_env() {
+    std::env::set_var("CLEAR_ENV_USER_AGENT", "");
+    std::env::set_var("CLEAR_ENV_PROXY", "");
+    std::env::set_var("CLEAR_ENV_HOSTS", "");
+    let config = ClientConfig::builder()
+        .from_env_prefixed("CLEAR_ENV")
+        .unwrap()
+        .combine(base())
+        .try_build()
+        .unwrap();
+    assert_eq!(config.user_agent, None);
+    assert_eq!(config.proxy, None);
+    assert_eq!(config.hosts, Vec::<String>::new());
+    assert_eq!(config.headers, vec!["accept"]);
+}
+
+#[test]
+fn clear_with_builder() {
+    let config = ClientConfig::builder()
+        .clear_proxy()
+        .user_agent(None)
+        .combine(base())
+        .try_build()
+        .unwrap();
+    assert_eq!(config.user_agent, None);
+    assert_eq!(config.proxy, None);
+    assert_eq!(config.hosts, vec!["a", "b"]);
+}
+
+#[test]
+fn partial_section_over_cleared_section() {
+    let overlay = ClientConfig::builder().map_proxy(|proxy| proxy.url("http://other".into()));
+    let cleared = ClientConfig::builder().clear_proxy();
+    let config = overlay
+        .combine(cleared)
+        .combine(base())
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config.proxy,
+        Some(ProxyConfig {
+            url: "http://other".into()
+        })
+    );
+}
+
+#[test]
+fn clear_list_in_file() {
+    let list: <ClientConfig as AppConfig>::Builder =
+        serde_yaml::from_str("hosts: [c, d]\nheaders: [accept, range]").unwrap();
+    let config = list
+        .combine(base())
+        .combine(ClientConfig::builder().default())
+        .try_build()
+        .unwrap();
+    assert_eq!(config.hosts, vec!["c", "d"]);
+    assert_eq!(config.headers, vec!["accept", "range"]);
+
+    let list: <ClientConfig as AppConfig>::Builder = serde_yaml::from_str("hosts: [c, d]").unwrap();
+    let overlay: <ClientConfig as AppConfig>::Builder =
+        serde_yaml::from_str("hosts: null").unwrap();
+    let config = overlay
+        .combine(list)
+        .combine(base())
+        .combine(ClientConfig::builder().default())
+        .try_build()
+        .unwrap();
+    assert_eq!(config.hosts, Vec::<String>::new());
+}
@@ -1,4 +1,6 @@
-use app_config::AppConfig;
+#![allow(clippy::bool_assert_comparison, clippy::needless_borrow)]
+
+use app_config::{AppConfig, AppConfigChoice};
 use serde::Deserialize;
 
 #[derive(AppConfig, Debug, PartialEq)]
@@ -81,6 +83,44 @@ struct OptionalNestedWithDefaultConfig {
     optional: Option<AttrDefaultConfig>,
 }
 
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct OptionalDeserializeConfig {
+    proxy: Option<String>,
+    #[config_field(parse = "bytes")]
+    max_size: Option<u64>,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+#[builder_derive(Clone, Debug)]
+#[builder_attr(serde(deny_unknown_fields))]
+struct BuilderAttrConfig {
+    #[builder_attr(serde(rename = "listen-port", alias = "port"))]
+    listen_port: u16,
+    #[builder_attr(doc = "The host to listen on")]
+    host: Option<String>,
+}
+
+type MaybePort = Option<u16>;
+
+fn parse_maybe_port(value: &str) -> Result<Option<u16>, std::num::ParseIntError> {
+    match value {
+        "none" => Ok(None),
+        port => port.parse().map(Some),
+    }
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct OptionPathConfig {
+    std_port: std::option::Option<u16>,
+    core_port: ::core::option::Option<u16>,
+    #[config_field(optional)]
+    alias_port: MaybePort,
+    #[config_field(required, parse_with = parse_maybe_port)]
+    required_port: Option<u16>,
+}
+
 #[derive(AppConfig, Debug, PartialEq)]
 enum EnumConfig {
     ChoiceA(BasicConfig),
@@ -428,7 +468,7 @@ fn optional_nested_ignore_defaults() {
 fn enum_config() {
     let mut builder = EnumConfig::builder();
     builder.choice_b = AttrDefaultConfig::builder().default();
-    builder.using = Some("choice_b".into());
+    builder.using = Some("choice_b".parse().unwrap());
     let result = builder.try_build();
     assert!(result.is_ok());
     let config = result.unwrap();
@@ -528,3 +568,141 @@ fn nested_deserialize_enum() {
         ),
     );
 }
+
+#[test]
+fn optional_field_paths() {
+    std::env::set_var("OPTION_PATHS_STD_PORT", "80");
+    std::env::set_var("OPTION_PATHS_ALIAS_PORT", "8080");
+    std::env::set_var("OPTION_PATHS_REQUIRED_PORT", "none");
+    let config = OptionPathConfig::builder()
+        .from_env_prefixed("OPTION_PATHS")
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(config.std_port, Some(80));
+    assert_eq!(config.core_port, None);
+    assert_eq!(config.alias_port, Some(8080));
+    assert_eq!(config.required_port, None);
+}
+
+#[test]
+fn required_option_field() {
+    let result = OptionPathConfig::builder().try_build();
+    assert_eq!(result.err().unwrap(), vec!["required_port"]);
+}
+
+#[test]
+fn combine_optional_fields() {
+    let file: <OptionalDeserializeConfig as AppConfig>::Builder =
+        serde_yaml::from_str("proxy: http://proxy\nmax_size: 1KiB").unwrap();
+    let config = OptionalDeserializeConfig::builder()
+        .from_env_prefixed("COMBINE_OPTIONAL")
+        .unwrap()
+        .combine(file)
+        .try_build()
+        .unwrap();
+    assert_eq!(config.proxy, Some("http://proxy".into()));
+    assert_eq!(config.max_size, Some(1024));
+}
+
+#[test]
+fn combine_explicit_null() {
+    let overlay: <OptionalDeserializeConfig as AppConfig>::Builder =
+        serde_yaml::from_str("proxy: null\nmax_size: ~").unwrap();
+    assert_eq!(overlay.proxy, Some(None));
+    assert_eq!(overlay.max_size, Some(None));
+    assert!(!overlay.is_empty());
+    let base: <OptionalDeserializeConfig as AppConfig>::Builder =
+        serde_yaml::from_str("proxy: http://proxy\nmax_size: 1KiB").unwrap();
+    let config = overlay.combine(base).try_build().unwrap();
+    assert_eq!(config.proxy, None);
+    assert_eq!(config.max_size, None);
+}
+
+#[test]
+fn unset_optional_fields() {
+    let builder = OptionalDeserializeConfig::builder();
+    assert_eq!(builder.proxy, None);
+    assert!(builder.is_empty());
+    let config = builder.try_build().unwrap();
+    assert_eq!(config.proxy, None);
+}
+
+#[test]
+fn deserialize_missing_sections() {
+    let builder: <NestedDeserializeConfig as AppConfig>::Builder =
+        serde_yaml::from_str("{}").unwrap();
+    assert!(builder.is_empty());
+    let builder: <NestedEnumDeserializeConfig as AppConfig>::Builder =
+        serde_yaml::from_str("nested: {using: choice_a}").unwrap();
+    assert!(builder.nested.choice_a.is_empty());
+    let builder: <DeserializeConfig as AppConfig>::Builder =
+        serde_yaml::from_str("field_b: test b").unwrap();
+    assert_eq!(builder.field_a, None);
+    assert_eq!(builder.field_b, Some("test b".into()));
+}
+
+#[test]
+fn forward_builder_attrs() {
+    let builder: <BuilderAttrConfig as AppConfig>::Builder =
+        serde_yaml::from_str("listen-port: 80").unwrap();
+    assert_eq!(builder.clone().listen_port, Some(80));
+    assert!(format!("{:?}", builder).contains("listen_port: Some(80)"));
+    let builder: <BuilderAttrConfig as AppConfig>::Builder =
+        serde_yaml::from_str("port: 8080\nhost: localhost").unwrap();
+    assert_eq!(builder.listen_port, Some(8080));
+    let result: Result<<BuilderAttrConfig as AppConfig>::Builder, _> =
+        serde_yaml::from_str("listen_port: 80");
+    assert!(result.is_err());
+}
+
+#[test]
+fn enum_choices() {
+    type Choice = <EnumConfig as AppConfigChoice>::Choices;
+    assert_eq!(Choice::ALL, &[Choice::ChoiceA, Choice::ChoiceB]);
+    assert_eq!(Choice::ChoiceB.to_string(), "choice_b");
+    assert_eq!("choice_a".parse(), Ok(Choice::ChoiceA));
+    assert_eq!(
+        "choice_c".parse::<Choice>(),
+        Err("expected one of `choice_a`, `choice_b`".to_string())
+    );
+}
+
+#[test]
+fn enum_invalid_choice() {
+    std::env::set_var("ENUM_INVALID_CHOICE_NESTED_USING", "choice_c");
+    let errors = NestedEnumConfig::builder()
+        .from_env_prefixed("ENUM_INVALID_CHOICE")
+        .err()
+        .unwrap();
+    assert_eq!(
+        errors,
+        vec![
+            "could not parse environment variable ENUM_INVALID_CHOICE_NESTED_USING=choice_c: \
+             expected one of `choice_a`, `choice_b`"
+        ]
+    );
+    let result: Result<<NestedEnumDeserializeConfig as AppConfig>::Builder, _> =
+        serde_yaml::from_str("nested: {using: choice_b}");
+    assert!(result
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("could not parse \"choice_b\": expected one of `choice_a`"));
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Debug, serde::Deserialize)]
+struct DerivePathConfig {
+    #[config_field(parse = "bytes")]
+    max_size: u64,
+    name: String,
+}
+
+#[test]
+fn builder_derive_path() {
+    let builder: <DerivePathConfig as AppConfig>::Builder =
+        serde_yaml::from_str("max_size: 1KiB").unwrap();
+    assert_eq!(builder.max_size, Some(1024));
+    assert_eq!(builder.name, None);
+}
@@ -0,0 +1,409 @@
+use app_config::alias::{AliasSource, Warning};
+use app_config::source::EnvSource;
+use app_config::AppConfig;
+use serde::Deserialize;
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct PostgresConfig {
+    host: String,
+    port: Option<u16>,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct SqliteConfig {
+    path: String,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+#[app_config(tag = "backend", externally_tagged)]
+enum DatabaseConfig {
+    Postgres(PostgresConfig),
+    Sqlite(SqliteConfig),
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+#[app_config(tag = "type")]
+enum StorageConfig {
+    Postgres(PostgresConfig),
+    Sqlite(SqliteConfig),
+}
+
+fn deserialize<C: AppConfig>(yaml: &str) -> C::Builder
+where
+    C::Builder: for<'de> Deserialize<'de>,
+{
+    serde_yaml::from_str(yaml).unwrap()
+}
+
+#[test]
+fn custom_tag() {
+    let config = deserialize::<StorageConfig>("type: sqlite\nsqlite: {path: db}")
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config,
+        StorageConfig::Sqlite(SqliteConfig { path: "db".into() })
+    );
+    let error = deserialize::<StorageConfig>("sqlite: {path: db}")
+        .try_build()
+        .err()
+        .unwrap();
+    assert_eq!(error, vec!["type is not specified, but sqlite is set"]);
+}
+
+#[test]
+fn custom_tag_from_env() {
+    std::env::set_var("CUSTOM_TAG_ENV_BACKEND", "sqlite");
+    std::env::set_var("CUSTOM_TAG_ENV_SQLITE_PATH", "db");
+    let config = DatabaseConfig::builder()
+        .from_env_prefixed("CUSTOM_TAG_ENV")
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config,
+        DatabaseConfig::Sqlite(SqliteConfig { path: "db".into() })
+    );
+    assert!(DatabaseConfig::unknown_env_vars("CUSTOM_TAG_ENV").is_empty());
+}
+
+#[test]
+fn externally_tagged() {
+    let config = deserialize::<DatabaseConfig>("postgres: {host: db}")
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config,
+        DatabaseConfig::Postgres(PostgresConfig {
+            host: "db".into(),
+            port: None,
+        })
+    );
+    let config =
+        deserialize::<DatabaseConfig>("backend: sqlite\npostgres: {host: db}\nsqlite: {path: db}")
+            .try_build()
+            .unwrap();
+    assert_eq!(
+        config,
+        DatabaseConfig::Sqlite(SqliteConfig { path: "db".into() })
+    );
+    let error = deserialize::<DatabaseConfig>("postgres: {host: db}\nsqlite: {path: db}")
+        .try_build()
+        .err()
+        .unwrap();
+    assert_eq!(error, vec!["backend is not specified, but postgres is set"]);
+}
+
+#[test]
+fn custom_tag_in_docs() {
+    assert!(DatabaseConfig::example_yaml().contains("# backend: <postgres | sqlite>  (required)"));
+    assert!(DatabaseConfig::example_env("APP").contains("# APP_BACKEND=<postgres | sqlite>"));
+    assert!(StorageConfig::reference_markdown("APP").contains("selected by `type` (`APP_TYPE`)"));
+}
+
+#[test]
+fn ignore_unselected_variant() {
+    std::env::set_var("UNSELECTED_ENV_BACKEND", "sqlite");
+    std::env::set_var("UNSELECTED_ENV_SQLITE_PATH", "db");
+    std::env::set_var("UNSELECTED_ENV_POSTGRES_PORT", "abc");
+    let env = EnvSource::new("UNSELECTED_ENV");
+    let source = AliasSource::new(&env, &DatabaseConfig::config_info());
+    let config = DatabaseConfig::builder()
+        .from_source(&source)
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config,
+        DatabaseConfig::Sqlite(SqliteConfig { path: "db".into() })
+    );
+    assert_eq!(
+        source.warnings(),
+        vec![Warning {
+            path: "postgres".to_string(),
+            source: "environment variable UNSELECTED_ENV_POSTGRES".to_string(),
+            message: "ignored because `postgres` is not selected: could not parse \
+                      environment variable UNSELECTED_ENV_POSTGRES_PORT=abc: \
+                      invalid digit found in string"
+                .to_string(),
+        }]
+    );
+}
+
+#[test]
+fn ignore_unselected_variant_env() {
+    std::env::set_var("UNSELECTED_PLAIN_ENV_BACKEND", "sqlite");
+    std::env::set_var("UNSELECTED_PLAIN_ENV_SQLITE_PATH", "db");
+    std::env::set_var("UNSELECTED_PLAIN_ENV_POSTGRES_PORT", "abc");
+    // the environment source drops the warning, so it's only returned through `AliasSource`
+    let config = DatabaseConfig::builder()
+        .from_env_prefixed("UNSELECTED_PLAIN_ENV")
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config,
+        DatabaseConfig::Sqlite(SqliteConfig { path: "db".into() })
+    );
+    let env = EnvSource::new("UNSELECTED_PLAIN_ENV");
+    let source = AliasSource::new(&env, &DatabaseConfig::config_info());
+    DatabaseConfig::builder().from_source(&source).unwrap();
+    let warnings = source.warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(
+        warnings[0].source,
+        "environment variable UNSELECTED_PLAIN_ENV_POSTGRES"
+    );
+}
+
+#[test]
+fn validate_selected_variant() {
+    std::env::set_var("SELECTED_ENV_BACKEND", "postgres");
+    std::env::set_var("SELECTED_ENV_POSTGRES_PORT", "abc");
+    assert_eq!(
+        DatabaseConfig::builder()
+            .from_env_prefixed("SELECTED_ENV")
+            .err()
+            .unwrap(),
+        vec![
+            "could not parse environment variable SELECTED_ENV_POSTGRES_PORT=abc: \
+             invalid digit found in string"
+        ]
+    );
+    // without the tag, any variant may be selected later
+    std::env::set_var("NO_TAG_ENV_POSTGRES_PORT", "abc");
+    assert!(DatabaseConfig::builder()
+        .from_env_prefixed("NO_TAG_ENV")
+        .is_err());
+}
+
+#[test]
+fn build_selected_variant() {
+    let config = DatabaseConfig::builder()
+        .using_sqlite()
+        .sqlite(SqliteConfig::builder().path("db".to_string()))
+        .postgres(PostgresConfig::builder().port(Some(5432)))
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config,
+        DatabaseConfig::Sqlite(SqliteConfig { path: "db".into() })
+    );
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct MemoryConfig {
+    #[config_field(default = 100_u32)]
+    capacity: u32,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+enum CacheConfig {
+    #[config_field(default)]
+    Memory(MemoryConfig),
+    Sqlite(SqliteConfig),
+}
+
+#[test]
+fn default_choice() {
+    let config = CacheConfig::builder().default().try_build().unwrap();
+    assert_eq!(config, CacheConfig::Memory(MemoryConfig { capacity: 100 }));
+    let config = CacheConfig::builder()
+        .using_sqlite()
+        .sqlite(SqliteConfig::builder().path("db".to_string()))
+        .combine(CacheConfig::builder().default())
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config,
+        CacheConfig::Sqlite(SqliteConfig { path: "db".into() })
+    );
+    assert!(CacheConfig::example_yaml().contains("\nusing: memory\n"));
+    assert!(CacheConfig::example_env("APP").contains("\nAPP_USING=memory\n"));
+    assert!(CacheConfig::reference_markdown("APP")
+        .contains("selected by `using` (`APP_USING`), `memory` by default:"));
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+#[app_config(infer_choice)]
+enum InferredConfig {
+    Postgres(PostgresConfig),
+    Sqlite(SqliteConfig),
+}
+
+#[test]
+fn infer_choice() {
+    let config = deserialize::<InferredConfig>("sqlite: {path: db}")
+        .combine(deserialize::<InferredConfig>("{}"))
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config,
+        InferredConfig::Sqlite(SqliteConfig { path: "db".into() })
+    );
+    let config =
+        deserialize::<InferredConfig>("using: sqlite\nsqlite: {path: db}\npostgres: {host: db}")
+            .try_build()
+            .unwrap();
+    assert_eq!(
+        config,
+        InferredConfig::Sqlite(SqliteConfig { path: "db".into() })
+    );
+    let error = deserialize::<InferredConfig>("sqlite: {path: db}\npostgres: {host: db}")
+        .try_build()
+        .err()
+        .unwrap();
+    assert_eq!(error, vec!["using is not specified, but postgres is set"]);
+    let error = deserialize::<InferredConfig>("{}")
+        .try_build()
+        .err()
+        .unwrap();
+    assert_eq!(error, vec!["using is not specified"]);
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct RedisConfig {
+    url: String,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct MemoryCacheConfig {
+    #[config_field(default = 100_u32)]
+    capacity: u32,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+#[app_config(infer_choice)]
+enum InferredCacheConfig {
+    #[config_field(default)]
+    Memory(MemoryCacheConfig),
+    Redis(RedisConfig),
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct CachedServiceConfig {
+    #[nested_field]
+    cache: InferredCacheConfig,
+}
+
+#[test]
+fn infer_choice_with_default() {
+    assert_eq!(InferredCacheConfig::builder().default().using, None);
+    let config = deserialize::<CachedServiceConfig>("cache: {redis: {url: x}}")
+        .combine(CachedServiceConfig::builder().default())
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config.cache,
+        InferredCacheConfig::Redis(RedisConfig {
+            url: "x".to_string()
+        })
+    );
+    let config = deserialize::<CachedServiceConfig>("cache: {memory: {capacity: 10}}")
+        .combine(CachedServiceConfig::builder().default())
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config.cache,
+        InferredCacheConfig::Memory(MemoryCacheConfig { capacity: 10 })
+    );
+    let config = deserialize::<CachedServiceConfig>("cache: {using: memory, redis: {url: x}}")
+        .combine(CachedServiceConfig::builder().default())
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config.cache,
+        InferredCacheConfig::Memory(MemoryCacheConfig { capacity: 100 })
+    );
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct ServiceConfig {
+    name: String,
+    #[nested_field]
+    storage: Option<StorageConfig>,
+}
+
+#[test]
+fn enum_builder_empty() {
+    assert!(StorageConfig::builder().is_empty());
+    assert!(!StorageConfig::builder().using_sqlite().is_empty());
+    assert!(!StorageConfig::builder()
+        .sqlite(SqliteConfig::builder().path("db".to_string()))
+        .is_empty());
+}
+
+#[test]
+fn optional_enum() {
+    let config = deserialize::<ServiceConfig>("name: service")
+        .try_build()
+        .unwrap();
+    assert_eq!(config.storage, None);
+    let config =
+        deserialize::<ServiceConfig>("name: service\nstorage: {type: sqlite, sqlite: {path: db}}")
+            .try_build()
+            .unwrap();
+    assert_eq!(
+        config.storage,
+        Some(StorageConfig::Sqlite(SqliteConfig { path: "db".into() }))
+    );
+    let error = deserialize::<ServiceConfig>("name: service\nstorage: {sqlite: {path: db}}")
+        .try_build()
+        .err()
+        .unwrap();
+    assert_eq!(error, vec!["type is not specified, but sqlite is set"]);
+}
+
+#[test]
+fn optional_enum_from_env() {
+    std::env::set_var("OPTIONAL_ENUM_ENV_NAME", "service");
+    std::env::set_var("OPTIONAL_ENUM_ENV_STORAGE_SQLITE_PATH", "db");
+    let error = ServiceConfig::builder()
+        .from_env_prefixed("OPTIONAL_ENUM_ENV")
+        .unwrap()
+        .try_build()
+        .err()
+        .unwrap();
+    assert_eq!(error, vec!["type is not specified, but sqlite is set"]);
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct LabeledStorageConfig {
+    label: String,
+    #[nested_field]
+    storage: StorageConfig,
+}
+
+#[test]
+fn interpolate_tag() {
+    // the tag holds a choice, so it can't hold a reference until it's interpolated
+    let error = serde_yaml::from_str::<<StorageConfig as AppConfig>::Builder>(
+        "type: ${STORAGE_KIND}\nsqlite: {path: db}",
+    )
+    .err()
+    .unwrap();
+    assert!(error
+        .to_string()
+        .contains("could not parse \"${STORAGE_KIND}\": expected one of `postgres`, `sqlite`"));
+
+    // but it can be referenced
+    let config = deserialize::<LabeledStorageConfig>(
+        "label: ${storage.type} storage\nstorage: {type: sqlite, sqlite: {path: db}}",
+    )
+    .interpolate()
+    .unwrap()
+    .try_build()
+    .unwrap();
+    assert_eq!(config.label, "sqlite storage");
+}
@@ -0,0 +1,227 @@
+use app_config::AppConfig;
+use serde::Deserialize;
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct PostgresConfig {
+    /// The user to connect as
+    username: String,
+    password: Option<String>,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct SqliteConfig {
+    #[config_field(default = "db.sqlite")]
+    path: String,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+enum DatabaseConfig {
+    /// Connect to a postgres server
+    Postgres(PostgresConfig),
+    Sqlite(SqliteConfig),
+}
+
+/// Configuration for the service
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct ServiceConfig {
+    /// The port to listen on
+    #[config_field(default = 8080_u16)]
+    port: u16,
+    #[config_field(default = [127, 0, 0, 1])]
+    addr: std::net::IpAddr,
+    #[nested_field]
+    database: DatabaseConfig,
+}
+
+#[test]
+fn example_yaml() {
+    assert_eq!(
+        ServiceConfig::example_yaml(),
+        r#"# Configuration for the service
+# The port to listen on
+port: 8080
+addr: "127.0.0.1"
+database:
+  # One of: postgres, sqlite
+  # using: <postgres | sqlite>  (required)
+  # Connect to a postgres server
+  # Used when `using: postgres`
+  # postgres:
+    # The user to connect as
+    # username: <String>  (required)
+    # password: <String>
+  # Used when `using: sqlite`
+  sqlite:
+    path: "db.sqlite"
+"#
+    );
+}
+
+#[test]
+fn example_yaml_loads() {
+    let builder: <ServiceConfig as AppConfig>::Builder =
+        serde_yaml::from_str(&ServiceConfig::example_yaml()).unwrap();
+    let overlay: <ServiceConfig as AppConfig>::Builder =
+        serde_yaml::from_str("database: {using: sqlite}").unwrap();
+    assert_eq!(
+        overlay.combine(builder).try_build().unwrap(),
+        ServiceConfig {
+            port: 8080,
+            addr: [127, 0, 0, 1].into(),
+            database: DatabaseConfig::Sqlite(SqliteConfig {
+                path: "db.sqlite".to_string(),
+            }),
+        }
+    );
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct GreetingConfig {
+    #[config_field(default = "say \"hi\"\tto e\u{301}\u{7}\\")]
+    greeting: String,
+}
+
+#[test]
+fn example_yaml_escapes() {
+    let example = GreetingConfig::example_yaml();
+    assert_eq!(
+        example,
+        "greeting: \"say \\\"hi\\\"\\tto e\u{301}\\u0007\\\\\"\n"
+    );
+    let builder: <GreetingConfig as AppConfig>::Builder = serde_yaml::from_str(&example).unwrap();
+    assert_eq!(
+        builder.try_build().unwrap().greeting,
+        "say \"hi\"\tto e\u{301}\u{7}\\"
+    );
+}
+
+#[test]
+fn example_toml() {
+    assert_eq!(
+        ServiceConfig::example_toml(),
+        r#"# Configuration for the service
+# The port to listen on
+port = 8080
+addr = "127.0.0.1"
+
+[database]
+# One of: postgres, sqlite
+# using = <postgres | sqlite>  (required)
+
+# Connect to a postgres server
+# Used when `using = "postgres"`
+[database.postgres]
+# The user to connect as
+# username = <String>  (required)
+# password = <String>
+
+# Used when `using = "sqlite"`
+[database.sqlite]
+path = "db.sqlite"
+"#
+    );
+}
+
+#[test]
+fn example_env() {
+    assert_eq!(
+        ServiceConfig::example_env("COOL_APP"),
+        r#"# Configuration for the service
+# The port to listen on
+COOL_APP_PORT=8080
+COOL_APP_ADDR=127.0.0.1
+# One of: postgres, sqlite
+# COOL_APP_DATABASE_USING=<postgres | sqlite>  (required)
+# Connect to a postgres server
+# Used when COOL_APP_DATABASE_USING=postgres
+# The user to connect as
+# COOL_APP_DATABASE_POSTGRES_USERNAME=<String>  (required)
+# COOL_APP_DATABASE_POSTGRES_PASSWORD=<String>
+# Used when COOL_APP_DATABASE_USING=sqlite
+COOL_APP_DATABASE_SQLITE_PATH=db.sqlite
+"#
+    );
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct ProfileConfig {
+    /// Log level | verbosity
+    #[config_field(default = "info", profile(dev = "debug"))]
+    log_level: String,
+    #[nested_field]
+    service: Option<ServiceConfig>,
+}
+
+#[test]
+fn reference_markdown() {
+    assert_eq!(
+        ProfileConfig::reference_markdown("APP"),
+        r#"<a id="root"></a>
+# ProfileConfig
+
+| Path | Type | Environment variable | Default | Required | Description |
+|---|---|---|---|---|---|
+| `log_level` | `String` | `APP_LOG_LEVEL` | `info`, dev: `debug` | no | Log level \| verbosity |
+| `service` | [ServiceConfig](#service) |  |  | no |  |
+
+<a id="service"></a>
+## `service`: ServiceConfig
+
+Configuration for the service
+
+| Path | Type | Environment variable | Default | Required | Description |
+|---|---|---|---|---|---|
+| `service.port` | `u16` | `APP_SERVICE_PORT` | `8080` | no | The port to listen on |
+| `service.addr` | `std::net::IpAddr` | `APP_SERVICE_ADDR` | `127.0.0.1` | no |  |
+| `service.database` | [DatabaseConfig](#service-database) |  |  | yes |  |
+
+<a id="service-database"></a>
+## `service.database`: DatabaseConfig
+
+One of the following, selected by `service.database.using` (`APP_SERVICE_DATABASE_USING`):
+
+| `using` | Config | Description |
+|---|---|---|
+| `postgres` | [PostgresConfig](#service-database-postgres) | Connect to a postgres server |
+| `sqlite` | [SqliteConfig](#service-database-sqlite) |  |
+
+<a id="service-database-postgres"></a>
+## `service.database.postgres`: PostgresConfig
+
+| Path | Type | Environment variable | Default | Required | Description |
+|---|---|---|---|---|---|
+| `service.database.postgres.username` | `String` | `APP_SERVICE_DATABASE_POSTGRES_USERNAME` |  | yes | The user to connect as |
+| `service.database.postgres.password` | `String` | `APP_SERVICE_DATABASE_POSTGRES_PASSWORD` |  | no |  |
+
+<a id="service-database-sqlite"></a>
+## `service.database.sqlite`: SqliteConfig
+
+| Path | Type | Environment variable | Default | Required | Description |
+|---|---|---|---|---|---|
+| `service.database.sqlite.path` | `String` | `APP_SERVICE_DATABASE_SQLITE_PATH` | `db.sqlite` | no |  |
+"#
+    );
+}
+
+#[test]
+fn reference_man() {
+    let man = SqliteConfig::reference_man("APP");
+    assert_eq!(
+        man,
+        ".TH SQLITECONFIG 5
+.SH SqliteConfig
+.TP
+.B path
+Type: String
+.br
+Environment variable: APP_PATH
+.br
+Default: db.sqlite
+"
+    );
+}
@@ -0,0 +1,250 @@
+mod common;
+
+use app_config::file::load_yaml_with_includes;
+use app_config::AppConfig;
+use common::TempDir;
+use serde::Deserialize;
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct DatabaseConfig {
+    host: String,
+    port: u16,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct ServiceConfig {
+    name: String,
+    log_level: String,
+    #[nested_field]
+    database: DatabaseConfig,
+}
+
+#[test]
+fn include_base() {
+    let dir = TempDir::with_files(
+        "include_base",
+        &[
+            (
+                "shared/base.yml",
+                "name: base\nlog_level: info\ndatabase: {host: db, port: 5432}",
+            ),
+            (
+                "service.yml",
+                "extends: shared/base.yml\nname: service\ndatabase: {port: 6432}",
+            ),
+        ],
+    );
+    let config =
+        load_yaml_with_includes::<<ServiceConfig as AppConfig>::Builder>(dir.join("service.yml"))
+            .unwrap()
+            .try_build()
+            .unwrap();
+    assert_eq!(config.name, "service");
+    assert_eq!(config.log_level, "info");
+    assert_eq!(config.database.host, "db");
+    assert_eq!(config.database.port, 6432);
+}
+
+#[test]
+fn include_list_priority() {
+    let dir = TempDir::with_files(
+        "include_list_priority",
+        &[
+            (
+                "a.yml",
+                "name: a\nlog_level: info\ndatabase: {host: a, port: 1}",
+            ),
+            ("b.yml", "log_level: debug\ndatabase: {host: b}"),
+            ("service.yml", "include: [a.yml, b.yml]\ndatabase: {}"),
+        ],
+    );
+    let config =
+        load_yaml_with_includes::<<ServiceConfig as AppConfig>::Builder>(dir.join("service.yml"))
+            .unwrap()
+            .try_build()
+            .unwrap();
+    assert_eq!(config.name, "a");
+    assert_eq!(config.log_level, "debug");
+    assert_eq!(config.database.host, "b");
+    assert_eq!(config.database.port, 1);
+}
+
+#[test]
+fn include_cycle() {
+    let dir = TempDir::with_files(
+        "include_cycle",
+        &[
+            ("a.yml", "include: b.yml\ndatabase: {}"),
+            ("b.yml", "include: a.yml\ndatabase: {}"),
+        ],
+    );
+    let result =
+        load_yaml_with_includes::<<ServiceConfig as AppConfig>::Builder>(dir.join("a.yml"));
+    let error = result.err().unwrap();
+    assert!(error.message.starts_with("include cycle"));
+    assert_eq!(
+        error.include_chain,
+        vec![dir.join("a.yml"), dir.join("b.yml"), dir.join("a.yml")]
+    );
+}
+
+#[test]
+fn include_parse_error_chain() {
+    let dir = TempDir::with_files(
+        "include_parse_error_chain",
+        &[
+            ("base.yml", "database: {port: not a number}"),
+            ("middle.yml", "include: base.yml\ndatabase: {}"),
+            ("service.yml", "include: middle.yml\ndatabase: {}"),
+        ],
+    );
+    let result =
+        load_yaml_with_includes::<<ServiceConfig as AppConfig>::Builder>(dir.join("service.yml"));
+    let error = result.err().unwrap();
+    assert_eq!(
+        error.include_chain,
+        vec![
+            dir.join("base.yml"),
+            dir.join("middle.yml"),
+            dir.join("service.yml")
+        ]
+    );
+    assert_eq!(
+        error.to_string().lines().skip(1).collect::<Vec<_>>(),
+        vec![
+            format!("  included from {}", dir.join("middle.yml").display()),
+            format!("  included from {}", dir.join("service.yml").display()),
+        ]
+    );
+}
+
+#[test]
+fn load_dir() {
+    let dir = TempDir::with_files(
+        "load_dir",
+        &[
+            (
+                "conf.d/10-base.yaml",
+                "name: base\nlog_level: info\ndatabase: {host: db, port: 5432}",
+            ),
+            ("conf.d/20-service.yaml", "name: service\ndatabase: {}"),
+            ("conf.d/30-database.yml", "database: {port: 6432}"),
+            ("conf.d/README.md", "not: [valid yaml"),
+        ],
+    );
+    let fragments = app_config::file::load_yaml_dir::<<ServiceConfig as AppConfig>::Builder>(
+        dir.join("conf.d"),
+        &["yaml", "yml"],
+    )
+    .unwrap();
+    assert_eq!(
+        fragments.provenance["name"].file,
+        dir.join("conf.d/20-service.yaml")
+    );
+    assert_eq!(
+        fragments.provenance["log_level"].file,
+        dir.join("conf.d/10-base.yaml")
+    );
+    assert_eq!(
+        fragments.provenance["database.port"].file,
+        dir.join("conf.d/30-database.yml")
+    );
+    let config = fragments.builder.try_build().unwrap();
+    assert_eq!(config.name, "service");
+    assert_eq!(config.log_level, "info");
+    assert_eq!(config.database.port, 6432);
+}
+
+#[test]
+fn load_dir_errors() {
+    let dir = TempDir::with_files(
+        "load_dir_errors",
+        &[
+            ("conf.d/10-invalid.yaml", "name: [unterminated"),
+            ("conf.d/20-valid.yaml", "name: service\ndatabase: {}"),
+            ("conf.d/30-wrong-type.yaml", "database: {port: many}"),
+        ],
+    );
+    let result = app_config::file::load_yaml_dir::<<ServiceConfig as AppConfig>::Builder>(
+        dir.join("conf.d"),
+        &["yaml"],
+    );
+    let errors = result.err().unwrap();
+    assert_eq!(
+        errors
+            .iter()
+            .map(|e| e.include_chain.clone())
+            .collect::<Vec<_>>(),
+        vec![
+            vec![dir.join("conf.d/10-invalid.yaml")],
+            vec![dir.join("conf.d/30-wrong-type.yaml")],
+        ]
+    );
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct ProxyConfig {
+    name: String,
+    proxy: Option<String>,
+    #[nested_field]
+    database: Option<DatabaseConfig>,
+}
+
+#[test]
+fn load_dir_provenance() {
+    let dir = TempDir::with_files(
+        "load_dir_provenance",
+        &[
+            ("shared/proxy.yml", "proxy: http://proxy"),
+            (
+                "conf.d/10-base.yaml",
+                "include: ../shared/proxy.yml\nname: base\ndatabase: {host: db, port: 5432}",
+            ),
+            ("conf.d/20-clear.yaml", "proxy: null\ndatabase: null"),
+        ],
+    );
+    let fragments = app_config::file::load_yaml_dir::<<ProxyConfig as AppConfig>::Builder>(
+        dir.join("conf.d"),
+        &["yaml"],
+    )
+    .unwrap();
+    assert_eq!(
+        fragments.provenance.keys().collect::<Vec<_>>(),
+        vec![
+            "database",
+            "database.host",
+            "database.port",
+            "name",
+            "proxy"
+        ]
+    );
+    assert_eq!(
+        fragments.provenance["name"].file,
+        dir.join("conf.d/10-base.yaml")
+    );
+    assert_eq!(
+        fragments.provenance["proxy"].file,
+        dir.join("conf.d/20-clear.yaml")
+    );
+    assert_eq!(
+        fragments.provenance["database"].file,
+        dir.join("conf.d/20-clear.yaml")
+    );
+    let config = fragments.builder.try_build().unwrap();
+    assert_eq!(config.proxy, None);
+    assert_eq!(config.database, None);
+
+    std::fs::remove_file(dir.join("conf.d/20-clear.yaml")).unwrap();
+    let fragments = app_config::file::load_yaml_dir::<<ProxyConfig as AppConfig>::Builder>(
+        dir.join("conf.d"),
+        &["yaml"],
+    )
+    .unwrap();
+    assert_eq!(
+        fragments.provenance["proxy"].file,
+        dir.join("conf.d/../shared/proxy.yml")
+    );
+}
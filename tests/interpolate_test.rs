@@ -0,0 +1,144 @@
+use app_config::AppConfig;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct ServerConfig {
+    host: String,
+    port: u16,
+    public_url: String,
+    data_dir: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Endpoint(String);
+
+impl FromStr for Endpoint {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        if value.contains("://") {
+            Ok(Endpoint(value.to_string()))
+        } else {
+            Err("expected a url like `https://host`".to_string())
+        }
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct ClientConfig {
+    scheme: String,
+    endpoint: Endpoint,
+    fallback: Option<Endpoint>,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct AppWithServerConfig {
+    name: String,
+    #[nested_field]
+    server: ServerConfig,
+    #[nested_field]
+    fallback: Option<ServerConfig>,
+}
+
+#[test]
+fn interpolate_fields() {
+    std::env::set_var("INTERPOLATE_FIELDS_HOME", "/home/test");
+    let config = ServerConfig::builder()
+        .host("example.com".into())
+        .port(8080)
+        .public_url("https://${host}:${port}".into())
+        .data_dir(Some("${env:INTERPOLATE_FIELDS_HOME}/.app".into()))
+        .interpolate()
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(config.public_url, "https://example.com:8080");
+    assert_eq!(config.data_dir, Some("/home/test/.app".into()));
+}
+
+#[test]
+fn interpolate_nested() {
+    let config = AppWithServerConfig::builder()
+        .name("app".into())
+        .map_server(|b| {
+            b.host("example.com".into())
+                .port(80)
+                .public_url("https://${server.host}/$${name}".into())
+                .data_dir(Some("/srv/${name}".into()))
+        })
+        .combine(
+            AppWithServerConfig::builder()
+                .map_server(|b| b.host("ignored".into()).public_url("ignored".into())),
+        )
+        .interpolate()
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(config.server.public_url, "https://example.com/${name}");
+    assert_eq!(config.server.data_dir, Some("/srv/app".into()));
+    assert_eq!(config.fallback, None);
+}
+
+#[test]
+fn interpolate_dotted_path() {
+    let config = AppWithServerConfig::builder()
+        .name("${server.host}".into())
+        .map_server(|b| {
+            b.host("example.com".into())
+                .port(80)
+                .public_url("https://$${host}".into())
+        })
+        .interpolate()
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(config.name, "example.com");
+    assert_eq!(config.server.public_url, "https://${host}");
+}
+
+#[test]
+fn interpolate_errors() {
+    let result = ServerConfig::builder()
+        .host("${public_url}".into())
+        .public_url("https://${host}:${port}".into())
+        .interpolate();
+    let errors = result.err().unwrap();
+    assert_eq!(
+        errors,
+        vec![
+            "public_url: reference cycle host -> public_url -> host",
+            "public_url: unresolved reference ${port}, no field with that path is set",
+        ]
+    );
+}
+
+#[test]
+fn interpolate_parsed_fields() {
+    let config = ClientConfig::builder()
+        .scheme("https".into())
+        .endpoint(Endpoint("${scheme}://example.com".into()))
+        .fallback(Some(Endpoint("${scheme}://fallback".into())))
+        .interpolate()
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(config.endpoint, Endpoint("https://example.com".into()));
+    assert_eq!(config.fallback, Some(Endpoint("https://fallback".into())));
+    let errors = ClientConfig::builder()
+        .scheme("example.com".into())
+        .endpoint(Endpoint("${scheme}".into()))
+        .interpolate()
+        .err()
+        .unwrap();
+    assert_eq!(
+        errors,
+        vec!["endpoint: could not parse \"example.com\": expected a url like `https://host`"]
+    );
+}
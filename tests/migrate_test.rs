@@ -0,0 +1,220 @@
+mod common;
+
+use app_config::file::{load_yaml_with_includes, upgrade_yaml};
+use app_config::AppConfig;
+use common::TempDir;
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::path::PathBuf;
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct DatabaseConfig {
+    host: String,
+    port: u16,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+#[app_config(version = 3, migrate(1 = migrations::v1_to_v2, 2 = migrations::v2_to_v3))]
+struct ServiceConfig {
+    name: String,
+    #[nested_field]
+    database: DatabaseConfig,
+}
+
+mod migrations {
+    use serde_yaml::{Mapping, Value};
+
+    // version 1 had `db_host` and `db_port` at the top level
+    pub fn v1_to_v2(value: &mut Value) -> Result<(), String> {
+        let mapping = value.as_mapping_mut().ok_or("expected a mapping")?;
+        let mut database = Mapping::new();
+        for (old, new) in [("db_host", "host"), ("db_port", "port")] {
+            if let Some(field) = mapping.remove(&Value::from(old)) {
+                database.insert(Value::from(new), field);
+            }
+        }
+        mapping.insert(Value::from("db"), Value::Mapping(database));
+        Ok(())
+    }
+
+    // version 2 called the database section `db`
+    pub fn v2_to_v3(value: &mut Value) -> Result<(), String> {
+        let mapping = value.as_mapping_mut().ok_or("expected a mapping")?;
+        if let Some(database) = mapping.remove(&Value::from("db")) {
+            mapping.insert(Value::from("database"), database);
+        }
+        Ok(())
+    }
+}
+
+fn load(path: PathBuf) -> ServiceConfig {
+    load_yaml_with_includes::<<ServiceConfig as AppConfig>::Builder>(path)
+        .unwrap()
+        .try_build()
+        .unwrap()
+}
+
+#[test]
+fn migrate_versions() {
+    let dir = TempDir::with_files(
+        "migrate_versions",
+        &[
+            (
+                "v1.yml",
+                "version: 1\nname: service\ndb_host: db\ndb_port: 5432",
+            ),
+            (
+                "v2.yml",
+                "version: 2\nname: service\ndb: {host: db, port: 5432}",
+            ),
+            (
+                "v3.yml",
+                "version: 3\nname: service\ndatabase: {host: db, port: 5432}",
+            ),
+        ],
+    );
+    let expected = ServiceConfig {
+        name: "service".to_string(),
+        database: DatabaseConfig {
+            host: "db".to_string(),
+            port: 5432,
+        },
+    };
+    assert_eq!(load(dir.join("v1.yml")), expected);
+    assert_eq!(load(dir.join("v2.yml")), expected);
+    assert_eq!(load(dir.join("v3.yml")), expected);
+}
+
+#[test]
+fn migrate_included_files() {
+    let dir = TempDir::with_files(
+        "migrate_included_files",
+        &[
+            (
+                "base.yml",
+                "version: 1\nname: base\ndb_host: db\ndb_port: 5432",
+            ),
+            (
+                "service.yml",
+                "version: 3\ninclude: base.yml\nname: service\ndatabase: {}",
+            ),
+        ],
+    );
+    let config = load(dir.join("service.yml"));
+    assert_eq!(config.name, "service");
+    assert_eq!(config.database.port, 5432);
+}
+
+#[test]
+fn migrate_newer_version() {
+    let dir = TempDir::with_files("migrate_newer_version", &[("v4.yml", "version: 4")]);
+    let error =
+        load_yaml_with_includes::<<ServiceConfig as AppConfig>::Builder>(dir.join("v4.yml"))
+            .err()
+            .unwrap();
+    assert_eq!(
+        error.message,
+        "version 4 is newer than the supported version 3"
+    );
+}
+
+#[test]
+fn upgrade_file() {
+    let dir = TempDir::with_files(
+        "upgrade_file",
+        &[(
+            "service.yml",
+            "version: 1\nname: service\ndb_host: db\ndb_port: 5432",
+        )],
+    );
+    let upgraded =
+        upgrade_yaml::<<ServiceConfig as AppConfig>::Builder>(dir.join("service.yml")).unwrap();
+    let expected: Value =
+        serde_yaml::from_str("version: 3\nname: service\ndatabase: {host: db, port: 5432}")
+            .unwrap();
+    assert_eq!(serde_yaml::from_str::<Value>(&upgraded).unwrap(), expected);
+    assert!(upgraded
+        .trim_start_matches("---\n")
+        .starts_with("version: 3\n"));
+}
+
+#[test]
+fn unversioned_overlay() {
+    let dir = TempDir::with_files(
+        "unversioned_overlay",
+        &[
+            (
+                "base.yml",
+                "version: 2\nname: base\ndb: {host: db, port: 5432}",
+            ),
+            ("service.yml", "include: base.yml\ndatabase: {port: 6432}"),
+        ],
+    );
+    let config = load(dir.join("service.yml"));
+    assert_eq!(config.name, "base");
+    assert_eq!(config.database.host, "db");
+    assert_eq!(config.database.port, 6432);
+}
+
+#[test]
+fn migrate_profile_sections() {
+    let dir = TempDir::with_files(
+        "migrate_profile_sections",
+        &[(
+            "service.yml",
+            "version: 1\nname: service\ndb_host: db\ndb_port: 5432\nprofiles:\n  prod:\n    db_port: 6432",
+        )],
+    );
+    app_config::profile::set_active_profile(Some("prod"));
+    let config =
+        load_yaml_with_includes::<<ServiceConfig as AppConfig>::Builder>(dir.join("service.yml"));
+    app_config::profile::set_active_profile(None);
+    let config = config.unwrap().try_build().unwrap();
+    assert_eq!(config.database.host, "db");
+    assert_eq!(config.database.port, 6432);
+
+    let upgraded =
+        upgrade_yaml::<<ServiceConfig as AppConfig>::Builder>(dir.join("service.yml")).unwrap();
+    let expected: Value = serde_yaml::from_str(
+        "version: 3\nname: service\ndatabase: {host: db, port: 5432}\nprofiles: {prod: {database: {port: 6432}}}",
+    )
+    .unwrap();
+    assert_eq!(serde_yaml::from_str::<Value>(&upgraded).unwrap(), expected);
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+#[app_config(
+    version = 3,
+    unversioned = 1,
+    migrate(1 = migrations::v1_to_v2, 2 = migrations::v2_to_v3)
+)]
+struct LegacyConfig {
+    name: String,
+    #[nested_field]
+    database: DatabaseConfig,
+}
+
+#[test]
+fn migrate_unversioned_file() {
+    let dir = TempDir::with_files(
+        "migrate_unversioned_file",
+        &[("service.yml", "name: service\ndb_host: db\ndb_port: 5432")],
+    );
+    let config =
+        load_yaml_with_includes::<<LegacyConfig as AppConfig>::Builder>(dir.join("service.yml"))
+            .unwrap()
+            .try_build()
+            .unwrap();
+    assert_eq!(config.database.host, "db");
+    assert_eq!(config.database.port, 5432);
+
+    let upgraded =
+        upgrade_yaml::<<LegacyConfig as AppConfig>::Builder>(dir.join("service.yml")).unwrap();
+    let expected: Value =
+        serde_yaml::from_str("version: 3\nname: service\ndatabase: {host: db, port: 5432}")
+            .unwrap();
+    assert_eq!(serde_yaml::from_str::<Value>(&upgraded).unwrap(), expected);
+}
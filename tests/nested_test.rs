@@ -0,0 +1,123 @@
+use app_config::AppConfig;
+use serde::Deserialize;
+use std::rc::Rc;
+use std::sync::Arc;
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct TlsConfig {
+    cert: String,
+    #[config_field(default = false)]
+    verify: bool,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct UpstreamConfig {
+    url: String,
+    /// Used when the upstream can't be reached
+    #[nested_field]
+    fallback: Option<Box<Self>>,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct ServiceConfig {
+    #[nested_field]
+    tls: Arc<TlsConfig>,
+    #[nested_field]
+    admin_tls: Option<Rc<TlsConfig>>,
+    #[nested_field]
+    upstream: UpstreamConfig,
+}
+
+fn upstream(urls: &[&str]) -> UpstreamConfig {
+    let (url, fallbacks) = urls.split_first().unwrap();
+    UpstreamConfig {
+        url: url.to_string(),
+        fallback: if fallbacks.is_empty() {
+            None
+        } else {
+            Some(Box::new(upstream(fallbacks)))
+        },
+    }
+}
+
+#[test]
+fn pointer_fields_from_env() {
+    std::env::set_var("NESTED_ENV_TLS_CERT", "cert.pem");
+    std::env::set_var("NESTED_ENV_UPSTREAM_URL", "http://a");
+    std::env::set_var("NESTED_ENV_UPSTREAM_FALLBACK_URL", "http://b");
+    std::env::set_var("NESTED_ENV_UPSTREAM_FALLBACK_FALLBACK_URL", "http://c");
+    let config = ServiceConfig::builder()
+        .from_env_prefixed("NESTED_ENV")
+        .unwrap()
+        .combine(ServiceConfig::builder().default())
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config,
+        ServiceConfig {
+            tls: Arc::new(TlsConfig {
+                cert: "cert.pem".to_string(),
+                verify: false,
+            }),
+            admin_tls: None,
+            upstream: upstream(&["http://a", "http://b", "http://c"]),
+        }
+    );
+}
+
+#[test]
+fn pointer_fields_from_yaml() {
+    let builder: <ServiceConfig as AppConfig>::Builder = serde_yaml::from_str(
+        "
+tls: {cert: cert.pem}
+admin_tls: {cert: admin.pem, verify: true}
+upstream:
+  url: http://a
+  fallback: {url: http://b}
+",
+    )
+    .unwrap();
+    let config = builder
+        .combine(ServiceConfig::builder().default())
+        .try_build()
+        .unwrap();
+    assert_eq!(config.admin_tls.unwrap().cert, "admin.pem");
+    assert_eq!(config.upstream, upstream(&["http://a", "http://b"]));
+}
+
+#[test]
+fn pointer_fields_combine() {
+    let base = UpstreamConfig::builder()
+        .url("http://a".to_string())
+        .fallback(UpstreamConfig::builder().url("http://b".to_string()));
+    let overlay = UpstreamConfig::builder().map_fallback(|fallback| {
+        fallback.map_fallback(|fallback| fallback.url("http://c".to_string()))
+    });
+    let config = overlay.combine(base).try_build().unwrap();
+    assert_eq!(config, upstream(&["http://a", "http://b", "http://c"]));
+}
+
+#[test]
+fn recursive_example() {
+    assert_eq!(
+        UpstreamConfig::example_yaml(),
+        "# url: <String>  (required)\n\
+         # Used when the upstream can't be reached\n\
+         # fallback: <UpstreamConfig>\n"
+    );
+}
+
+#[test]
+fn recursive_reference() {
+    let reference = ServiceConfig::reference_markdown("APP");
+    assert!(reference.contains("<a id=\"upstream\"></a>"));
+    assert!(!reference.contains("<a id=\"upstream-fallback\"></a>"));
+    assert!(reference.contains(
+        "| `upstream.fallback` | [UpstreamConfig](#upstream) |  |  | no | \
+         Used when the upstream can't be reached |"
+    ));
+    assert!(ServiceConfig::reference_man("APP").contains("UpstreamConfig, see UPSTREAM\n"));
+}
@@ -0,0 +1,255 @@
+use app_config::AppConfig;
+use serde::Deserialize;
+use std::time::Duration;
+
+fn parse_level(value: &str) -> Result<u8, String> {
+    match value {
+        "low" => Ok(1),
+        "high" => Ok(10),
+        _ => Err(format!("unknown level `{}`", value)),
+    }
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct CacheConfig {
+    #[config_field(parse = "duration", default = Duration::from_secs(60))]
+    ttl: Duration,
+    #[config_field(parse = "bytes")]
+    max_size: u64,
+    #[config_field(parse = "bool")]
+    enabled: bool,
+    #[config_field(parse = "percent")]
+    eviction_threshold: Option<f64>,
+    #[config_field(parse_with = parse_level)]
+    level: u8,
+}
+
+#[test]
+fn parse_env() {
+    std::env::set_var("PARSE_ENV_TTL", "1h30m");
+    std::env::set_var("PARSE_ENV_MAX_SIZE", "512MiB");
+    std::env::set_var("PARSE_ENV_ENABLED", "yes");
+    std::env::set_var("PARSE_ENV_EVICTION_THRESHOLD", "90%");
+    std::env::set_var("PARSE_ENV_LEVEL", "high");
+    let config = CacheConfig::builder()
+        .from_env_prefixed("PARSE_ENV")
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config,
+        CacheConfig {
+            ttl: Duration::from_secs(5400),
+            max_size: 512 * 1024 * 1024,
+            enabled: true,
+            eviction_threshold: Some(0.9),
+            level: 10,
+        }
+    );
+}
+
+#[test]
+fn parse_env_errors() {
+    std::env::set_var("PARSE_ENV_ERRORS_TTL", "soon");
+    std::env::set_var("PARSE_ENV_ERRORS_LEVEL", "medium");
+    let errors = CacheConfig::builder()
+        .from_env_prefixed("PARSE_ENV_ERRORS")
+        .err()
+        .unwrap();
+    assert_eq!(
+        errors,
+        vec![
+            "could not parse environment variable PARSE_ENV_ERRORS_TTL=soon: \
+             expected a duration like `30s` or `1h30m`, found `soon`",
+            "could not parse environment variable PARSE_ENV_ERRORS_LEVEL=medium: \
+             unknown level `medium`",
+        ]
+    );
+}
+
+#[test]
+fn parse_yaml() {
+    let builder: <CacheConfig as AppConfig>::Builder = serde_yaml::from_str(
+        "ttl: 30s\nmax_size: 1GB\nenabled: on\neviction_threshold: 0.5\nlevel: low",
+    )
+    .unwrap();
+    assert_eq!(
+        builder.try_build().unwrap(),
+        CacheConfig {
+            ttl: Duration::from_secs(30),
+            max_size: 1_000_000_000,
+            enabled: true,
+            eviction_threshold: Some(0.5),
+            level: 1,
+        }
+    );
+
+    let builder: <CacheConfig as AppConfig>::Builder =
+        serde_yaml::from_str("max_size: 1024\nenabled: true\nlevel: low").unwrap();
+    let config = builder
+        .combine(CacheConfig::builder().default())
+        .try_build()
+        .unwrap();
+    assert_eq!(config.ttl, Duration::from_secs(60));
+    assert_eq!(config.eviction_threshold, None);
+
+    let error = serde_yaml::from_str::<<CacheConfig as AppConfig>::Builder>("ttl: 30 seconds")
+        .err()
+        .unwrap();
+    assert!(error
+        .to_string()
+        .contains("could not parse \"30 seconds\": expected a duration"));
+}
+
+fn parse_addr(value: &str) -> Result<std::net::SocketAddr, std::net::AddrParseError> {
+    match value.parse() {
+        Ok(addr) => Ok(addr),
+        Err(_) => format!("{}:8080", value).parse(),
+    }
+}
+
+fn parse_hosts(value: &str) -> Result<Vec<String>, String> {
+    let hosts: Vec<String> = value
+        .split(',')
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .collect();
+    if hosts.is_empty() {
+        Err("expected at least one host".to_string())
+    } else {
+        Ok(hosts)
+    }
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct ClusterConfig {
+    #[config_field(parse_with = parse_addr)]
+    listen: std::net::SocketAddr,
+    #[config_field(parse_with = parse_hosts)]
+    peers: Vec<String>,
+    port: u16,
+}
+
+#[test]
+fn parse_with_env() {
+    std::env::set_var("PARSE_WITH_ENV_LISTEN", "127.0.0.1");
+    std::env::set_var("PARSE_WITH_ENV_PEERS", "a.local, b.local,");
+    std::env::set_var("PARSE_WITH_ENV_PORT", "80");
+    let config = ClusterConfig::builder()
+        .from_env_prefixed("PARSE_WITH_ENV")
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(config.listen, "127.0.0.1:8080".parse().unwrap());
+    assert_eq!(config.peers, vec!["a.local", "b.local"]);
+}
+
+#[test]
+fn parse_with_env_errors() {
+    std::env::set_var("PARSE_WITH_ENV_ERRORS_LISTEN", "localhost");
+    std::env::set_var("PARSE_WITH_ENV_ERRORS_PEERS", ",");
+    std::env::set_var("PARSE_WITH_ENV_ERRORS_PORT", "eighty");
+    let errors = ClusterConfig::builder()
+        .from_env_prefixed("PARSE_WITH_ENV_ERRORS")
+        .err()
+        .unwrap();
+    assert_eq!(
+        errors,
+        vec![
+            "could not parse environment variable PARSE_WITH_ENV_ERRORS_LISTEN=localhost: \
+             invalid socket address syntax",
+            "could not parse environment variable PARSE_WITH_ENV_ERRORS_PEERS=,: \
+             expected at least one host",
+            "could not parse environment variable PARSE_WITH_ENV_ERRORS_PORT=eighty: \
+             invalid digit found in string",
+        ]
+    );
+}
+
+// doesn't implement `Deserialize`, so it can only be parsed from scalars
+#[derive(Debug, PartialEq)]
+struct Level(u8);
+
+fn parse_level_name(value: &str) -> Result<Level, String> {
+    parse_level(value).map(Level)
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct LoggingConfig {
+    #[config_field(parse_with = parse_level_name)]
+    level: Level,
+    #[config_field(parse_with = parse_hosts)]
+    targets: Vec<String>,
+}
+
+#[test]
+fn parse_with_file_lists() {
+    let builder: <LoggingConfig as AppConfig>::Builder =
+        serde_yaml::from_str("level: low\ntargets: [stdout, syslog]").unwrap();
+    let config = builder.try_build().unwrap();
+    assert_eq!(config.level, Level(1));
+    assert_eq!(config.targets, vec!["stdout", "syslog"]);
+
+    let builder: <LoggingConfig as AppConfig>::Builder =
+        serde_yaml::from_str("level: high\ntargets: stdout, syslog").unwrap();
+    assert_eq!(
+        builder.try_build().unwrap().targets,
+        vec!["stdout", "syslog"]
+    );
+
+    let error = serde_yaml::from_str::<<LoggingConfig as AppConfig>::Builder>("level: [low]")
+        .err()
+        .unwrap();
+    assert!(error
+        .to_string()
+        .contains("invalid type: sequence, expected a string, number or boolean"));
+}
+
+#[derive(Debug, PartialEq)]
+struct Mode(String);
+
+impl std::str::FromStr for Mode {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, ()> {
+        match value {
+            "fast" | "safe" => Ok(Mode(value.to_string())),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct ModeConfig {
+    mode: Mode,
+    port: u16,
+}
+
+#[test]
+fn parse_from_str_without_display() {
+    std::env::set_var("PARSE_OPAQUE_MODE", "fast");
+    let config = ModeConfig::builder()
+        .from_env_prefixed("PARSE_OPAQUE")
+        .unwrap()
+        .port(80)
+        .try_build()
+        .unwrap();
+    assert_eq!(config.mode, Mode("fast".to_string()));
+
+    std::env::set_var("PARSE_OPAQUE_ERRORS_MODE", "slow");
+    std::env::set_var("PARSE_OPAQUE_ERRORS_PORT", "http");
+    let errors = ModeConfig::builder()
+        .from_env_prefixed("PARSE_OPAQUE_ERRORS")
+        .err()
+        .unwrap();
+    assert_eq!(
+        errors,
+        vec![
+            "could not parse environment variable PARSE_OPAQUE_ERRORS_MODE=slow",
+            "could not parse environment variable PARSE_OPAQUE_ERRORS_PORT=http: \
+             invalid digit found in string",
+        ]
+    );
+}
@@ -0,0 +1,225 @@
+mod common;
+
+use app_config::file::Origin;
+use app_config::profile::Profiles;
+use app_config::AppConfig;
+use common::TempDir;
+use serde::Deserialize;
+use std::sync::Mutex;
+
+// tests that set the active profile can't run at the same time
+static ACTIVE_PROFILE: Mutex<()> = Mutex::new(());
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct ServerConfig {
+    #[config_field(default = 8080_u16, profile(prod = 80_u16))]
+    port: u16,
+    #[config_field(default = [127, 0, 0, 1], profile(prod = [0, 0, 0, 0]))]
+    addr: std::net::IpAddr,
+    #[config_field(profile(dev = true))]
+    debug: Option<bool>,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct AppConfigWithServer {
+    #[config_field(default = "app")]
+    name: String,
+    #[nested_field]
+    server: ServerConfig,
+}
+
+#[test]
+fn profile_defaults() {
+    let config = ServerConfig::builder()
+        .default_for_profile(None)
+        .try_build()
+        .unwrap();
+    assert_eq!(config.port, 8080);
+    assert_eq!(config.addr, std::net::IpAddr::from([127, 0, 0, 1]));
+    assert_eq!(config.debug, None);
+
+    let config = ServerConfig::builder()
+        .default_for_profile(Some("prod"))
+        .try_build()
+        .unwrap();
+    assert_eq!(config.port, 80);
+    assert_eq!(config.addr, std::net::IpAddr::from([0, 0, 0, 0]));
+    assert_eq!(config.debug, None);
+
+    let config = ServerConfig::builder()
+        .default_for_profile(Some("dev"))
+        .try_build()
+        .unwrap();
+    assert_eq!(config.port, 8080);
+    assert_eq!(config.debug, Some(true));
+}
+
+#[test]
+fn profile_nested_defaults() {
+    let config = AppConfigWithServer::builder()
+        .default_for_profile(Some("prod"))
+        .try_build()
+        .unwrap();
+    assert_eq!(config.name, "app");
+    assert_eq!(config.server.port, 80);
+}
+
+#[test]
+fn active_profile() {
+    let _lock = ACTIVE_PROFILE.lock().unwrap();
+    app_config::profile::set_active_profile(Some("prod"));
+    let config = ServerConfig::builder().default().try_build().unwrap();
+    app_config::profile::set_active_profile(None);
+    assert_eq!(config.port, 80);
+}
+
+#[test]
+fn profile_file_sections() {
+    let config_yml = r#"
+name: from file
+server:
+    port: 3000
+    addr: 10.0.0.1
+profiles:
+    prod:
+        name: from prod section
+        server:
+            port: 443
+            addr: 10.0.0.2
+"#;
+    let profiles: Profiles<<AppConfigWithServer as AppConfig>::Builder> =
+        serde_yaml::from_str(config_yml).unwrap();
+    let config = profiles
+        .select(Some("prod"))
+        .combine(AppConfigWithServer::builder().default_for_profile(Some("prod")))
+        .try_build()
+        .unwrap();
+    assert_eq!(config.name, "from prod section");
+    assert_eq!(config.server.port, 443);
+
+    let profiles: Profiles<<AppConfigWithServer as AppConfig>::Builder> =
+        serde_yaml::from_str(config_yml).unwrap();
+    let config = profiles
+        .select(Some("dev"))
+        .combine(AppConfigWithServer::builder().default_for_profile(Some("dev")))
+        .try_build()
+        .unwrap();
+    assert_eq!(config.name, "from file");
+    assert_eq!(config.server.port, 3000);
+    assert_eq!(config.server.debug, Some(true));
+}
+
+#[test]
+fn profile_file_loader() {
+    let dir = TempDir::with_files(
+        "profile_file_loader",
+        &[
+            ("base.yml", "server: {addr: 10.0.0.1}"),
+            ("prod.yml", "name: from prod.yml"),
+            (
+                "conf.d/config.yml",
+                r#"
+include: ../base.yml
+name: from file
+server:
+    port: 3000
+profiles:
+    prod:
+        include: ../prod.yml
+        server:
+            port: 443
+    dev:
+        server:
+            debug: true
+"#,
+            ),
+        ],
+    );
+    let info = AppConfigWithServer::config_info();
+    let _lock = ACTIVE_PROFILE.lock().unwrap();
+    app_config::profile::set_active_profile(Some("prod"));
+    let loaded = app_config::file::load_yaml_strict::<<AppConfigWithServer as AppConfig>::Builder>(
+        dir.join("conf.d/config.yml"),
+        &info,
+    );
+    let fragments = app_config::file::load_yaml_dir::<<AppConfigWithServer as AppConfig>::Builder>(
+        dir.join("conf.d"),
+        &["yml"],
+    );
+    app_config::profile::set_active_profile(None);
+
+    let (builder, warnings) = loaded.unwrap();
+    assert!(warnings.is_empty());
+    let config = builder.try_build().unwrap();
+    assert_eq!(config.name, "from prod.yml");
+    assert_eq!(config.server.port, 443);
+    assert_eq!(config.server.addr, std::net::IpAddr::from([10, 0, 0, 1]));
+    assert_eq!(config.server.debug, None);
+
+    let fragments = fragments.unwrap();
+    assert_eq!(
+        fragments.provenance["server.port"],
+        Origin {
+            file: dir.join("conf.d/config.yml"),
+            profile: Some("prod".to_string()),
+        }
+    );
+    assert_eq!(
+        fragments.provenance["name"],
+        Origin {
+            file: dir.join("conf.d/../prod.yml"),
+            profile: None,
+        }
+    );
+
+    let config = app_config::file::load_yaml_with_includes::<
+        <AppConfigWithServer as AppConfig>::Builder,
+    >(dir.join("conf.d/config.yml"))
+    .unwrap()
+    .try_build()
+    .unwrap();
+    assert_eq!(config.name, "from file");
+    assert_eq!(config.server.port, 3000);
+    assert_eq!(config.server.debug, None);
+}
+
+#[test]
+fn profile_unknown_keys() {
+    let dir = TempDir::with_files(
+        "profile_unknown_keys",
+        &[(
+            "config.yml",
+            "name: app\nprofiles:\n    prod:\n        server: {prot: 443}",
+        )],
+    );
+    let info = AppConfigWithServer::config_info();
+    let value: serde_yaml::Value =
+        serde_yaml::from_str(&std::fs::read_to_string(dir.join("config.yml")).unwrap()).unwrap();
+    assert_eq!(
+        app_config::strict::unknown_keys(&info, &value),
+        vec!["unknown key `profiles.prod.server.prot`, did you mean `profiles.prod.server.port`?"]
+    );
+
+    let _lock = ACTIVE_PROFILE.lock().unwrap();
+    app_config::profile::set_active_profile(Some("prod"));
+    let loaded = app_config::file::load_yaml_strict::<<AppConfigWithServer as AppConfig>::Builder>(
+        dir.join("config.yml"),
+        &info,
+    );
+    app_config::profile::set_active_profile(None);
+    assert_eq!(
+        loaded.err().unwrap().message,
+        "unknown key `profiles.prod.server.prot`, did you mean `profiles.prod.server.port`?"
+    );
+}
+
+#[test]
+fn profile_reference() {
+    let _lock = ACTIVE_PROFILE.lock().unwrap();
+    app_config::profile::set_active_profile(Some("prod"));
+    let reference = ServerConfig::reference_markdown("APP");
+    app_config::profile::set_active_profile(None);
+    assert!(reference.contains("| `8080`, prod (active): `80` |"));
+}
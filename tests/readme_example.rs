@@ -1,3 +1,5 @@
+#![allow(clippy::bool_assert_comparison, clippy::redundant_static_lifetimes)]
+
 use app_config::AppConfig;
 use serde::Deserialize;
 
@@ -0,0 +1,84 @@
+use app_config::AppConfig;
+use serde::Deserialize;
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+#[app_config(rename_all = "kebab-case")]
+struct PoolConfig {
+    max_connections: u32,
+    #[config_field(rename = "timeout")]
+    timeout_secs: u64,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+#[app_config(rename_all = "lowercase")]
+enum DatabaseConfig {
+    PostgreSQL(PoolConfig),
+    #[config_field(rename = "sqlite3")]
+    Sqlite(PoolConfig),
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+#[app_config(rename_all = "camelCase")]
+struct ServiceConfig {
+    listen_port: u16,
+    #[nested_field]
+    database: DatabaseConfig,
+}
+
+fn pool(max_connections: u32, timeout_secs: u64) -> PoolConfig {
+    PoolConfig {
+        max_connections,
+        timeout_secs,
+    }
+}
+
+#[test]
+fn rename_in_file() {
+    let builder: <ServiceConfig as AppConfig>::Builder = serde_yaml::from_str(
+        "listenPort: 80\ndatabase: {using: postgresql, postgresql: {max-connections: 10, timeout: 5}}",
+    )
+    .unwrap();
+    assert_eq!(
+        builder.try_build().unwrap(),
+        ServiceConfig {
+            listen_port: 80,
+            database: DatabaseConfig::PostgreSQL(pool(10, 5)),
+        }
+    );
+}
+
+#[test]
+fn rename_in_env() {
+    std::env::set_var("RENAME_ENV_LISTENPORT", "80");
+    std::env::set_var("RENAME_ENV_DATABASE_USING", "sqlite3");
+    std::env::set_var("RENAME_ENV_DATABASE_SQLITE3_MAX_CONNECTIONS", "1");
+    std::env::set_var("RENAME_ENV_DATABASE_SQLITE3_TIMEOUT", "2");
+    let config = ServiceConfig::builder()
+        .from_env_prefixed("RENAME_ENV")
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(config.database, DatabaseConfig::Sqlite(pool(1, 2)));
+    assert!(ServiceConfig::unknown_env_vars("RENAME_ENV").is_empty());
+}
+
+#[test]
+fn rename_in_builder() {
+    let builder = DatabaseConfig::builder().using_postgre_sql();
+    assert_eq!(builder.using.unwrap().to_string(), "postgresql");
+    assert_eq!(
+        PoolConfig::builder().try_build().err().unwrap(),
+        vec!["max-connections", "timeout"]
+    );
+}
+
+#[test]
+fn rename_in_docs() {
+    let reference = ServiceConfig::reference_markdown("CONFIG");
+    assert!(reference.contains("`database.postgresql.max-connections`"));
+    assert!(reference.contains("`CONFIG_DATABASE_POSTGRESQL_MAX_CONNECTIONS`"));
+    assert!(ServiceConfig::example_yaml().contains("listenPort:"));
+}
@@ -0,0 +1,285 @@
+use app_config::AppConfig;
+use serde_json::json;
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct PostgresConfig {
+    /// The user to connect as
+    username: String,
+    password: Option<String>,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct SqliteConfig {
+    #[config_field(default = "db.sqlite")]
+    path: std::path::PathBuf,
+}
+
+/// The database to use
+#[derive(AppConfig, Debug, PartialEq)]
+enum DatabaseConfig {
+    /// Connect to a postgres server
+    Postgres(PostgresConfig),
+    Sqlite(SqliteConfig),
+}
+
+/// Configuration for the service
+#[derive(AppConfig, Debug, PartialEq)]
+struct ServiceConfig {
+    /// The port to listen on.
+    ///
+    /// Ports below 1024 require root.
+    #[config_field(default = 8080_u16)]
+    port: u16,
+    #[config_field(default = [127, 0, 0, 1])]
+    addr: std::net::IpAddr,
+    name: String,
+    ratio: Option<f64>,
+    #[nested_field]
+    database: DatabaseConfig,
+    #[nested_field]
+    fallback: Option<PostgresConfig>,
+}
+
+#[test]
+fn struct_schema() {
+    assert_eq!(
+        PostgresConfig::json_schema(),
+        json!({
+            "type": "object",
+            "properties": {
+                "username": {"type": "string", "description": "The user to connect as"},
+                "password": {"type": ["string", "null"]},
+            },
+            "required": ["username"],
+        })
+    );
+}
+
+#[test]
+fn nested_schema() {
+    let postgres = PostgresConfig::json_schema();
+    assert_eq!(
+        ServiceConfig::json_schema(),
+        json!({
+            "type": "object",
+            "description": "Configuration for the service",
+            "properties": {
+                "port": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "default": 8080,
+                    "description": "The port to listen on.\n\nPorts below 1024 require root.",
+                },
+                "addr": {"type": "string", "default": "127.0.0.1"},
+                "name": {"type": "string"},
+                "ratio": {"type": ["number", "null"]},
+                "database": {
+                    "description": "The database to use",
+                    "oneOf": [
+                        {
+                            "type": "object",
+                            "description": "Connect to a postgres server",
+                            "properties": {
+                                "using": {"const": "postgres"},
+                                "postgres": postgres,
+                            },
+                            "required": ["using"],
+                        },
+                        {
+                            "type": "object",
+                            "properties": {
+                                "using": {"const": "sqlite"},
+                                "sqlite": {
+                                    "type": "object",
+                                    "properties": {
+                                        "path": {"type": "string", "default": "db.sqlite"},
+                                    },
+                                },
+                            },
+                            "required": ["using"],
+                        },
+                    ],
+                },
+                "fallback": {
+                    "type": ["object", "null"],
+                    "properties": postgres["properties"],
+                    "required": ["username"],
+                },
+            },
+            "required": ["name", "database"],
+        })
+    );
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct UpstreamConfig {
+    url: String,
+    #[nested_field]
+    fallback: Option<Box<Self>>,
+}
+
+#[test]
+fn recursive_schema() {
+    assert_eq!(
+        UpstreamConfig::json_schema(),
+        json!({
+            "$ref": "#/$defs/UpstreamConfig",
+            "$defs": {
+                "UpstreamConfig": {
+                    "type": "object",
+                    "properties": {
+                        "url": {"type": "string"},
+                        "fallback": {
+                            "anyOf": [{"$ref": "#/$defs/UpstreamConfig"}, {"type": "null"}],
+                        },
+                    },
+                    "required": ["url"],
+                },
+            },
+        })
+    );
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct RouteConfig {
+    path: String,
+    #[nested_field]
+    backend: Option<Box<BackendConfig>>,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct BackendConfig {
+    url: String,
+    #[nested_field]
+    route: Option<Box<RouteConfig>>,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct RouterConfig {
+    #[nested_field]
+    primary: BackendConfig,
+}
+
+#[test]
+fn mutually_recursive_schema() {
+    assert_eq!(
+        RouterConfig::json_schema(),
+        json!({
+            "type": "object",
+            "properties": {
+                "primary": {"$ref": "#/$defs/BackendConfig"},
+            },
+            "required": ["primary"],
+            "$defs": {
+                "BackendConfig": {
+                    "type": "object",
+                    "properties": {
+                        "url": {"type": "string"},
+                        "route": {
+                            "type": ["object", "null"],
+                            "properties": {
+                                "path": {"type": "string"},
+                                "backend": {
+                                    "anyOf": [{"$ref": "#/$defs/BackendConfig"}, {"type": "null"}],
+                                },
+                            },
+                            "required": ["path"],
+                        },
+                    },
+                    "required": ["url"],
+                },
+            },
+        })
+    );
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+enum CacheConfig {
+    #[config_field(default)]
+    Sqlite(SqliteConfig),
+    Postgres(PostgresConfig),
+}
+
+#[test]
+fn default_variant_schema() {
+    assert_eq!(
+        CacheConfig::json_schema(),
+        json!({
+            "oneOf": [
+                {
+                    "type": "object",
+                    "properties": {
+                        "using": {"const": "sqlite"},
+                        "sqlite": SqliteConfig::json_schema(),
+                    },
+                },
+                {
+                    "type": "object",
+                    "properties": {
+                        "using": {"const": "postgres"},
+                        "postgres": PostgresConfig::json_schema(),
+                    },
+                    "required": ["using"],
+                },
+            ],
+        })
+    );
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct WorkerConfig {
+    #[config_field(default = 4_u32)]
+    threads: Option<u32>,
+    #[nested_field]
+    cache: Option<CacheConfig>,
+}
+
+#[test]
+fn nullable_schema() {
+    assert_eq!(
+        WorkerConfig::json_schema(),
+        json!({
+            "type": "object",
+            "properties": {
+                "threads": {"type": ["integer", "null"], "minimum": 0, "default": 4},
+                "cache": {"anyOf": [CacheConfig::json_schema(), {"type": "null"}]},
+            },
+        })
+    );
+}
+
+fn parse_hosts(value: &str) -> Result<Vec<String>, String> {
+    Ok(value.split(',').map(String::from).collect())
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct LimitsConfig {
+    #[config_field(parse = "bytes", default = 1024_u64)]
+    max_size: u64,
+    #[config_field(parse = "bool")]
+    enabled: bool,
+    #[config_field(parse = "duration", default = std::time::Duration::from_secs(5400))]
+    timeout: std::time::Duration,
+    #[config_field(parse = "duration")]
+    idle: Option<std::time::Duration>,
+    #[config_field(parse_with = parse_hosts)]
+    hosts: Vec<String>,
+}
+
+#[test]
+fn parsed_schema() {
+    assert_eq!(
+        LimitsConfig::json_schema(),
+        json!({
+            "type": "object",
+            "properties": {
+                "max_size": {"type": ["integer", "string"], "minimum": 0, "default": "1024"},
+                "enabled": {"type": ["boolean", "string"]},
+                "timeout": {"type": "string", "default": "90m"},
+                "idle": {"type": ["string", "null"]},
+                "hosts": {"type": ["array", "string"], "items": {"type": "string"}},
+            },
+            "required": ["enabled", "hosts"],
+        })
+    );
+}
@@ -0,0 +1,161 @@
+mod common;
+
+use app_config::source::DirSource;
+use app_config::AppConfig;
+use common::TempDir;
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct DatabaseConfig {
+    host: String,
+    port: u16,
+    password: Option<String>,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct ServiceConfig {
+    name: String,
+    #[nested_field]
+    database: DatabaseConfig,
+}
+
+#[test]
+fn dir_source() {
+    let dir = TempDir::with_files(
+        "dir_source",
+        &[
+            ("name", "service\n"),
+            ("database__host", "db"),
+            ("database..port", "5432\r\n"),
+            ("database/password", "secret\n\n"),
+        ],
+    );
+    let config = ServiceConfig::builder()
+        .from_source(&DirSource::new(&dir).unwrap())
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config,
+        ServiceConfig {
+            name: "service".into(),
+            database: DatabaseConfig {
+                host: "db".into(),
+                port: 5432,
+                password: Some("secret".into()),
+            }
+        }
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn dir_source_kubernetes_layout() {
+    use std::os::unix::fs::symlink;
+    let dir = TempDir::new("dir_source_kubernetes_layout");
+    std::fs::create_dir(dir.join("..2022_04_01_12_00_00.123")).unwrap();
+    std::fs::write(dir.join("..2022_04_01_12_00_00.123/name"), "service\n").unwrap();
+    std::fs::write(
+        dir.join("..2022_04_01_12_00_00.123/database__port"),
+        "5432\n",
+    )
+    .unwrap();
+    symlink("..2022_04_01_12_00_00.123", dir.join("..data")).unwrap();
+    symlink("..data/name", dir.join("name")).unwrap();
+    symlink("..data/database__port", dir.join("database__port")).unwrap();
+    let builder = ServiceConfig::builder()
+        .from_source(&DirSource::new(&dir).unwrap())
+        .unwrap();
+    assert_eq!(builder.name, Some("service".into()));
+    assert_eq!(builder.database.port, Some(5432));
+    assert_eq!(builder.database.host, None);
+}
+
+#[test]
+fn dir_source_parse_error() {
+    let dir = TempDir::with_files("dir_source_parse_error", &[("database__port", "many\n")]);
+    let result = ServiceConfig::builder().from_source(&DirSource::new(&dir).unwrap());
+    assert_eq!(
+        result.err().unwrap(),
+        vec![format!(
+            "could not parse file {}=many: invalid digit found in string",
+            dir.join("database__port").display()
+        )]
+    );
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[app_config(rename_all = "camelCase")]
+struct PoolConfig {
+    max_connections: u32,
+    idle_timeout: Option<u32>,
+}
+
+#[test]
+fn dir_source_renamed_fields() {
+    let dir = TempDir::with_files(
+        "dir_source_renamed_fields",
+        &[("maxConnections", "10\n"), ("IDLETIMEOUT", "30\n")],
+    );
+    let config = PoolConfig::builder()
+        .from_source(&DirSource::new(&dir).unwrap())
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config,
+        PoolConfig {
+            max_connections: 10,
+            idle_timeout: Some(30),
+        }
+    );
+}
+
+struct MapSource(std::collections::HashMap<&'static str, &'static str>);
+
+impl app_config::source::Source for MapSource {
+    fn get(&self, path: &str) -> Result<Option<String>, String> {
+        Ok(self.0.get(path).map(|value| value.to_string()))
+    }
+
+    fn describe(&self, path: &str) -> String {
+        format!("map key {}", path)
+    }
+
+    fn has_prefix(&self, path: &str) -> bool {
+        let prefix = format!("{}.", path);
+        self.0.keys().any(|key| key.starts_with(&prefix))
+    }
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct ReplicaConfig {
+    name: String,
+    #[nested_field]
+    replica: Option<DatabaseConfig>,
+}
+
+#[test]
+fn custom_source() {
+    let source = MapSource(
+        [
+            ("name", "service"),
+            ("replica.host", "replica"),
+            ("replica.port", "5433"),
+        ]
+        .into_iter()
+        .collect(),
+    );
+    let config = ReplicaConfig::builder()
+        .from_source(&source)
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(
+        config.replica,
+        Some(DatabaseConfig {
+            host: "replica".to_string(),
+            port: 5433,
+            password: None,
+        })
+    );
+}
@@ -0,0 +1,184 @@
+mod common;
+
+use app_config::file::load_yaml_strict;
+use app_config::AppConfig;
+use common::TempDir;
+use serde::Deserialize;
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct PostgresConfig {
+    host: String,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct SqliteConfig {
+    path: String,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+enum DatabaseConfig {
+    Postgres(PostgresConfig),
+    Sqlite(SqliteConfig),
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+#[builder_derive(Deserialize)]
+struct ServiceConfig {
+    port: u16,
+    #[nested_field]
+    database: DatabaseConfig,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct ProxyConfig {
+    url: String,
+}
+
+#[derive(AppConfig, Debug, PartialEq)]
+struct UpstreamConfig {
+    url: String,
+    #[nested_field]
+    fallback: Option<Box<Self>>,
+    #[nested_field]
+    #[config_field(clear_on_empty)]
+    proxy: Option<ProxyConfig>,
+}
+
+#[test]
+fn strict_file() {
+    let dir = TempDir::with_files(
+        "strict_file",
+        &[(
+            "service.yml",
+            "port: 80\ndatabase: {using: postgres, postgres: {host: db}}",
+        )],
+    );
+    let config = load_yaml_strict::<<ServiceConfig as AppConfig>::Builder>(
+        dir.join("service.yml"),
+        &ServiceConfig::config_info(),
+    )
+    .unwrap()
+    .0
+    .try_build()
+    .unwrap();
+    assert_eq!(config.port, 80);
+}
+
+#[test]
+fn strict_file_unknown_keys() {
+    let dir = TempDir::with_files("strict_file_unknown_keys", &[("service.yml", "prot: 80\ndatabase: {using: postgres, postgress: {hots: db}, postgres: {hots: db}, sqlite: {path: db, mode: rw}}")]);
+    let error = load_yaml_strict::<<ServiceConfig as AppConfig>::Builder>(
+        dir.join("service.yml"),
+        &ServiceConfig::config_info(),
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+        error.message,
+        "unknown key `prot`, did you mean `port`?; \
+         unknown key `database.postgress`, did you mean `database.postgres`?; \
+         unknown key `database.postgres.hots`, did you mean `database.postgres.host`?; \
+         unknown key `database.sqlite.mode`"
+    );
+}
+
+#[test]
+fn strict_env() {
+    std::env::set_var("STRICT_ENV_PORT", "80");
+    std::env::set_var("STRICT_ENV_DATABASE_USING", "sqlite");
+    assert!(ServiceConfig::unknown_env_vars("STRICT_ENV").is_empty());
+    std::env::set_var("STRICT_ENV_PROT", "80");
+    std::env::set_var("STRICT_ENV_DATABASE_SQLITE_PTH", "db");
+    std::env::set_var("STRICT_ENV_OTHER", "1");
+    assert_eq!(
+        ServiceConfig::unknown_env_vars("STRICT_ENV"),
+        vec![
+            "environment variable STRICT_ENV_DATABASE_SQLITE_PTH does not match any field, \
+             did you mean STRICT_ENV_DATABASE_SQLITE_PATH?",
+            "environment variable STRICT_ENV_OTHER does not match any field",
+            "environment variable STRICT_ENV_PROT does not match any field, \
+             did you mean STRICT_ENV_PORT?",
+        ]
+    );
+}
+
+#[test]
+fn strict_env_loader() {
+    std::env::set_var("STRICT_LOADER_PORT", "80");
+    std::env::set_var("STRICT_LOADER_DATABASE_USING", "sqlite");
+    std::env::set_var("STRICT_LOADER_DATABASE_SQLITE_PATH", "db");
+    let config = <ServiceConfig as AppConfig>::Builder::new_from_env_strict("STRICT_LOADER")
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(config.port, 80);
+
+    std::env::set_var("STRICT_LOADER_DATABASE_SQLITE_PTH", "db");
+    assert_eq!(
+        ServiceConfig::builder()
+            .from_env_strict("STRICT_LOADER")
+            .err()
+            .unwrap(),
+        vec![
+            "environment variable STRICT_LOADER_DATABASE_SQLITE_PTH does not match any field, \
+             did you mean STRICT_LOADER_DATABASE_SQLITE_PATH?"
+        ]
+    );
+
+    std::env::set_var("STRICT_LOADER_PORT", "eighty");
+    let errors = ServiceConfig::builder()
+        .from_env_strict("STRICT_LOADER")
+        .err()
+        .unwrap();
+    assert_eq!(
+        errors,
+        vec![
+            "could not parse environment variable STRICT_LOADER_PORT=eighty: \
+             invalid digit found in string",
+            "environment variable STRICT_LOADER_DATABASE_SQLITE_PTH does not match any field, \
+             did you mean STRICT_LOADER_DATABASE_SQLITE_PATH?",
+        ]
+    );
+}
+
+#[test]
+fn strict_env_recursive() {
+    std::env::set_var("STRICT_RECURSIVE_URL", "http://a");
+    std::env::set_var("STRICT_RECURSIVE_FALLBACK_URL", "http://b");
+    std::env::set_var("STRICT_RECURSIVE_FALLBACK_FALLBACK_URL", "http://c");
+    std::env::set_var(
+        "STRICT_RECURSIVE_FALLBACK_FALLBACK_PROXY_URL",
+        "http://proxy",
+    );
+    assert!(UpstreamConfig::unknown_env_vars("STRICT_RECURSIVE").is_empty());
+    std::env::set_var("STRICT_RECURSIVE_FALLBACK_FALLBACK_URI", "http://c");
+    assert_eq!(
+        UpstreamConfig::unknown_env_vars("STRICT_RECURSIVE"),
+        vec![
+            "environment variable STRICT_RECURSIVE_FALLBACK_FALLBACK_URI does not match any field"
+        ]
+    );
+}
+
+#[test]
+fn strict_env_clear_section() {
+    std::env::set_var("STRICT_CLEAR_URL", "http://a");
+    std::env::set_var("STRICT_CLEAR_PROXY", "");
+    assert!(UpstreamConfig::unknown_env_vars("STRICT_CLEAR").is_empty());
+    let config = <UpstreamConfig as AppConfig>::Builder::new_from_env_strict("STRICT_CLEAR")
+        .unwrap()
+        .try_build()
+        .unwrap();
+    assert_eq!(config.proxy, None);
+}
+
+#[test]
+fn strict_env_profile() {
+    std::env::set_var("CONFIG_PORT", "80");
+    std::env::set_var("CONFIG_DATABASE_USING", "sqlite");
+    std::env::set_var(app_config::profile::PROFILE_ENV_VAR, "strict");
+    assert!(ServiceConfig::unknown_env_vars("CONFIG").is_empty());
+}